@@ -1,10 +1,49 @@
 //! Defines cache file format
 
 use crate::repository::Repository;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
-use std::{fs::OpenOptions, path::Path};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fs::{self, File, TryLockError},
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Version of the cache format written by this version of rhq.
+const CACHE_VERSION: u64 = 2;
+
+/// Migrations from the older formats, where `MIGRATIONS[i]` upgrades version `i` to `i + 1`.
+const MIGRATIONS: &[fn(&mut Value) -> Result<()>] = &[migrate_v0, migrate_v1];
+
+/// Version 0 is the unversioned format, which has the same layout as version 1.
+///
+/// The metadata of repositories added in version 1 are optional, and are filled on the next refresh.
+fn migrate_v0(cache: &mut Value) -> Result<()> {
+    if !cache.is_object() {
+        return Err(anyhow!("the cache is not a JSON object"));
+    }
+    Ok(())
+}
+
+/// Version 1 stores the name of VCS capitalized (e.g. `"Git"`), while version 2 uses
+/// the name of the backend (e.g. `"git"`).
+fn migrate_v1(cache: &mut Value) -> Result<()> {
+    let repos = cache
+        .pointer_mut("/inner/repositories")
+        .and_then(Value::as_array_mut);
+    for repo in repos.into_iter().flatten() {
+        // an invalid entry is left as is, and dropped on loading.
+        if let Some(vcs) = repo.get_mut("vcs") {
+            if let Some(name) = vcs.as_str() {
+                *vcs = Value::from(name.to_lowercase());
+            }
+        }
+    }
+    Ok(())
+}
 
 // inner representation of cache format.
 #[derive(Default, Debug, Serialize, Deserialize)]
@@ -14,22 +53,159 @@ pub struct CacheData {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Cache {
+    #[serde(default)]
+    version: u64,
     timestamp: DateTime<Local>,
     inner: Option<CacheData>,
+    /// whether the cache file was broken
+    #[serde(skip)]
+    needs_rebuild: bool,
+    /// entries as loaded from (or last saved to) the cache file, used to merge
+    /// the changes of other processes on saving.
+    #[serde(skip)]
+    base: HashMap<PathBuf, Value>,
+}
+
+enum LoadError {
+    /// The cache file cannot be read as any known format.
+    Corrupt(anyhow::Error),
+    /// The cache file is written by a newer version of rhq.
+    Unsupported(u64),
 }
 
 impl Cache {
+    /// Load the cache file.
+    ///
+    /// A cache in the older format is migrated to the current one.
+    /// If the cache file is broken, an empty cache is returned instead
+    /// and the caller is expected to rebuild it (see `needs_rebuild`).
     pub fn new(cache_path: &Path) -> Result<Self> {
-        if cache_path.exists() {
-            let mut file = OpenOptions::new().read(true).open(cache_path)?;
-            let cache = serde_json::from_reader(&mut file)?;
-            Ok(cache)
-        } else {
-            Ok(Cache {
-                timestamp: Local::now(),
-                inner: None,
-            })
+        if !cache_path.exists() {
+            return Ok(Self::empty());
+        }
+
+        let content = {
+            let _lock = CacheLock::acquire(cache_path, false)?;
+            fs::read_to_string(cache_path)?
+        };
+        match Self::parse(&content) {
+            Ok(cache) => Ok(cache),
+            Err(LoadError::Unsupported(version)) => Err(anyhow!(
+                "the cache format version {} is not supported (supported up to {}); upgrade rhq",
+                version,
+                CACHE_VERSION
+            )),
+            Err(LoadError::Corrupt(err)) => {
+                log::warn!("the cache file is broken: {}", err);
+                Ok(Cache {
+                    needs_rebuild: true,
+                    ..Self::empty()
+                })
+            }
+        }
+    }
+
+    fn empty() -> Self {
+        Cache {
+            version: CACHE_VERSION,
+            timestamp: Local::now(),
+            inner: None,
+            needs_rebuild: false,
+            base: HashMap::new(),
+        }
+    }
+
+    fn parse(content: &str) -> Result<Self, LoadError> {
+        Self::parse_with(content, true)
+    }
+
+    /// Parse the content of the cache file, dropping the broken entries.
+    ///
+    /// A warning is logged for each dropped entry if `warn_dropped` is set.
+    fn parse_with(content: &str, warn_dropped: bool) -> Result<Self, LoadError> {
+        let mut value: Value =
+            serde_json::from_str(content).map_err(|err| LoadError::Corrupt(err.into()))?;
+        let version = match value.get("version") {
+            Some(version) => version
+                .as_u64()
+                .ok_or_else(|| LoadError::Corrupt(anyhow!("invalid format version")))?,
+            None => 0,
+        };
+        if version > CACHE_VERSION {
+            return Err(LoadError::Unsupported(version));
+        }
+
+        for (from, migrate) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            migrate(&mut value).map_err(LoadError::Corrupt)?;
+            value["version"] = Value::from(from as u64 + 1);
+        }
+
+        // The repositories are deserialized one by one, so that a broken entry
+        // does not make the whole cache unusable.
+        let repos = match value.pointer_mut("/inner/repositories") {
+            Some(repos) => std::mem::replace(repos, Value::Array(vec![])),
+            None => Value::Null,
+        };
+        let mut cache: Cache =
+            serde_json::from_value(value).map_err(|err| LoadError::Corrupt(err.into()))?;
+        if let Value::Array(repos) = repos {
+            let data = cache.get_mut();
+            for repo in repos {
+                match serde_json::from_value(repo) {
+                    Ok(repo) => data.repositories.push(repo),
+                    Err(err) if warn_dropped => {
+                        log::warn!("dropped a broken entry of the repository cache: {}", err)
+                    }
+                    Err(_) => {}
+                }
+            }
         }
+        cache.base = cache.snapshot();
+        Ok(cache)
+    }
+
+    fn snapshot(&self) -> HashMap<PathBuf, Value> {
+        self.get_opt()
+            .into_iter()
+            .flat_map(|data| &data.repositories)
+            .filter_map(|repo| Some((repo.path().to_owned(), serde_json::to_value(repo).ok()?)))
+            .collect()
+    }
+
+    /// Returns whether the cache file was broken and the repositories must be rebuilt.
+    pub fn needs_rebuild(&self) -> bool {
+        self.needs_rebuild
+    }
+
+    /// Move the broken cache file aside, and returns the path of the backup.
+    ///
+    /// If another process has already replaced the broken cache file in the meantime,
+    /// the new one is loaded instead and `None` is returned.
+    pub fn backup(&mut self, cache_path: &Path) -> Result<Option<PathBuf>> {
+        let _lock = CacheLock::acquire(cache_path, true)?;
+        match fs::read_to_string(cache_path) {
+            Ok(content) => {
+                if let Ok(cache) = Self::parse(&content) {
+                    *self = cache;
+                    return Ok(None);
+                }
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+                self.needs_rebuild = false;
+                return Ok(None);
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        let mut file_name = cache_path
+            .file_name()
+            .map(|name| name.to_os_string())
+            .unwrap_or_default();
+        file_name.push(Local::now().format(".broken-%Y%m%d%H%M%S").to_string());
+        let backup_path = cache_path.with_file_name(file_name);
+        fs::rename(cache_path, &backup_path)?;
+        self.needs_rebuild = false;
+        Ok(Some(backup_path))
     }
 
     pub fn get_opt(&self) -> Option<&CacheData> {
@@ -43,10 +219,270 @@ impl Cache {
         self.inner.as_mut().unwrap()
     }
 
+    /// Save the cache file.
+    ///
+    /// The cache file is re-read under the exclusive lock, and the entries changed by
+    /// other processes since the cache was loaded are merged before writing.
     pub fn dump(&mut self, cache_path: &Path) -> Result<()> {
+        let lock = CacheLock::acquire(cache_path, true)?;
+        self.dump_locked(cache_path, lock)
+    }
+
+    /// Save the cache file as `dump`, unless another process holds the lock.
+    ///
+    /// Returns whether the cache file was saved.
+    pub fn try_dump(&mut self, cache_path: &Path) -> Result<bool> {
+        match CacheLock::try_acquire(cache_path)? {
+            Some(lock) => self.dump_locked(cache_path, lock).map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    fn dump_locked(&mut self, cache_path: &Path, _lock: CacheLock) -> Result<()> {
+        let theirs = match fs::read_to_string(cache_path) {
+            // The broken entries were already reported when the cache was loaded.
+            Ok(content) => match Self::parse_with(&content, false) {
+                Ok(cache) => cache.inner.map(|data| data.repositories),
+                Err(LoadError::Unsupported(version)) => {
+                    return Err(anyhow!(
+                    "the cache file has been overwritten with the format version {}; upgrade rhq",
+                    version
+                ))
+                }
+                // The broken cache is overwritten.
+                Err(LoadError::Corrupt(..)) => None,
+            },
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(theirs) = theirs {
+            let ours = std::mem::take(&mut self.get_mut().repositories);
+            self.get_mut().repositories = merge(&self.base, ours, theirs);
+        }
+
+        self.version = CACHE_VERSION;
         self.timestamp = Local::now();
         crate::util::write_content(cache_path, |f| {
             serde_json::to_writer_pretty(f, &self).map_err(Into::into)
-        })
+        })?;
+        self.base = self.snapshot();
+        Ok(())
+    }
+}
+
+/// Three-way merge of the repositories, by their paths.
+///
+/// The entries not changed since `base` are taken from `theirs` (and dropped if
+/// `theirs` has removed them), while the entries added, changed or removed on `ours`
+/// take precedence. The entries added by `theirs` are appended.
+fn merge(
+    base: &HashMap<PathBuf, Value>,
+    ours: Vec<Repository>,
+    theirs: Vec<Repository>,
+) -> Vec<Repository> {
+    let is_unchanged = |repo: &Repository| {
+        base.get(repo.path())
+            .is_some_and(|value| serde_json::to_value(repo).ok().as_ref() == Some(value))
+    };
+    let mut theirs: HashMap<PathBuf, Repository> = theirs
+        .into_iter()
+        .map(|repo| (repo.path().to_owned(), repo))
+        .collect();
+
+    let mut merged = Vec::with_capacity(ours.len());
+    for repo in ours {
+        let their_repo = theirs.remove(repo.path());
+        if is_unchanged(&repo) {
+            merged.extend(their_repo);
+        } else {
+            merged.push(repo);
+        }
+    }
+    let mut added: Vec<Repository> = theirs
+        .into_values()
+        .filter(|repo| !base.contains_key(repo.path()))
+        .collect();
+    added.sort_by(|a, b| a.path().cmp(b.path()));
+    merged.extend(added);
+    merged
+}
+
+/// Advisory lock of the cache file, held until dropped.
+///
+/// The lock is held only while the cache file is read or written, so that a
+/// long-running command does not block the other invocations of rhq.
+#[derive(Debug)]
+struct CacheLock {
+    _file: File,
+}
+
+impl CacheLock {
+    /// Lock the cache file at `cache_path`, waiting for other processes to release it.
+    ///
+    /// The lock is taken on a separate file next to the cache, since the cache itself
+    /// is replaced on every save.
+    fn acquire(cache_path: &Path, exclusive: bool) -> Result<Self> {
+        let file = Self::open(cache_path)?;
+        let locked = if exclusive {
+            file.try_lock()
+        } else {
+            file.try_lock_shared()
+        };
+        match locked {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                eprintln!("Waiting for another rhq process to release the cache...");
+                if exclusive {
+                    file.lock()?;
+                } else {
+                    file.lock_shared()?;
+                }
+            }
+            Err(TryLockError::Error(err)) => return Err(err.into()),
+        }
+        Ok(CacheLock { _file: file })
+    }
+
+    /// Lock the cache file exclusively, or returns `None` if another process holds the lock.
+    fn try_acquire(cache_path: &Path) -> Result<Option<Self>> {
+        let file = Self::open(cache_path)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(CacheLock { _file: file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(err)) => Err(err.into()),
+        }
+    }
+
+    fn open(cache_path: &Path) -> Result<File> {
+        let mut lock_name = cache_path
+            .file_name()
+            .map(|name| name.to_os_string())
+            .unwrap_or_default();
+        lock_name.push(".lock");
+        let lock_path = cache_path.with_file_name(lock_name);
+        if let Some(dir) = lock_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&lock_path)
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcs::Vcs;
+
+    #[test]
+    fn load_unversioned_cache() {
+        let content = r#"{
+            "timestamp": "2020-01-01T00:00:00+09:00",
+            "inner": {
+                "repositories": [
+                    {
+                        "name": "rhq",
+                        "path": "/home/user/rhq/github.com/ubnt-intrepid/rhq",
+                        "vcs": "Git",
+                        "remote": { "url": "https://github.com/ubnt-intrepid/rhq.git" }
+                    }
+                ]
+            }
+        }"#;
+        let cache = Cache::parse(content).ok().unwrap();
+        assert_eq!(cache.version, CACHE_VERSION);
+        let repos = &cache.get_opt().unwrap().repositories;
+        assert_eq!(repos.len(), 1);
+        assert_eq!(repos[0].name(), "rhq");
+        assert_eq!(repos[0].vcs(), Vcs::GIT);
+        assert!(repos[0].added_at().is_none());
+    }
+
+    #[test]
+    fn drop_broken_entries() {
+        let content = r#"{
+            "version": 2,
+            "timestamp": "2020-01-01T00:00:00+09:00",
+            "inner": {
+                "repositories": [
+                    { "name": "a", "path": "/src/a", "vcs": "git" },
+                    { "name": "b", "vcs": "git" },
+                    { "name": "c", "path": "/src/c", "vcs": 42 }
+                ]
+            }
+        }"#;
+        let cache = Cache::parse(content).ok().unwrap();
+        let repos = &cache.get_opt().unwrap().repositories;
+        assert_eq!(repos.len(), 1);
+        assert_eq!(repos[0].name(), "a");
+    }
+
+    #[test]
+    fn reject_broken_cache() {
+        assert!(matches!(
+            Cache::parse("{\"timestamp\": "),
+            Err(LoadError::Corrupt(..))
+        ));
+        assert!(matches!(
+            Cache::parse("{\"version\": 2, \"inner\": null}"),
+            Err(LoadError::Corrupt(..))
+        ));
+        assert!(matches!(
+            Cache::parse("{\"version\": 100}"),
+            Err(LoadError::Unsupported(100))
+        ));
+    }
+
+    fn repo(name: &str, description: &str) -> Repository {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "path": format!("/src/{}", name),
+            "vcs": "git",
+            "description": description,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn merge_concurrent_changes() {
+        let loaded = [repo("a", ""), repo("b", ""), repo("c", ""), repo("d", "")];
+        let base = loaded
+            .iter()
+            .map(|repo| (repo.path().to_owned(), serde_json::to_value(repo).unwrap()))
+            .collect();
+
+        // ours: modified a, removed b, added e
+        let ours = vec![
+            repo("a", "ours"),
+            repo("c", ""),
+            repo("d", ""),
+            repo("e", ""),
+        ];
+        // theirs: modified a and c, removed d, added f
+        let theirs = vec![
+            repo("a", "theirs"),
+            repo("b", ""),
+            repo("c", "theirs"),
+            repo("f", ""),
+        ];
+
+        let merged: Vec<_> = merge(&base, ours, theirs)
+            .into_iter()
+            .map(|repo| {
+                (
+                    repo.name().to_owned(),
+                    repo.description().map(ToOwned::to_owned),
+                )
+            })
+            .collect();
+        let expected: Vec<_> = [("a", "ours"), ("c", "theirs"), ("e", ""), ("f", "")]
+            .iter()
+            .map(|&(name, desc)| (name.to_owned(), Some(desc.to_owned())))
+            .collect();
+        assert_eq!(merged, expected);
     }
 }
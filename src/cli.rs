@@ -1,10 +1,16 @@
 mod add;
 mod clone;
 mod completion;
+mod foreach;
 mod import;
 mod list;
 mod new;
+mod path;
 mod refresh;
+mod remove;
+mod shell_init;
+mod status;
+mod sync;
 
 use crate::Workspace;
 use anyhow::Result;
@@ -17,6 +23,14 @@ pub struct Args {
 
     #[arg(short = 'v', long = "verbose", help = "Use verbose output")]
     pub verbose: bool,
+
+    #[arg(
+        short = 'j',
+        long = "jobs",
+        global = true,
+        help = "Number of repositories processed in parallel"
+    )]
+    pub jobs: Option<usize>,
 }
 
 #[derive(Debug, clap::Subcommand)]
@@ -25,10 +39,16 @@ pub enum Ops {
     Add(add::AddCommand),
     Clone(clone::CloneCommand),
     Completion(completion::CompletionCommand),
+    Foreach(foreach::ForeachCommand),
     Import(import::ImportCommand),
     List(list::ListCommand),
     New(new::NewCommand),
+    Path(path::PathCommand),
     Refresh(refresh::RefreshCommand),
+    Remove(remove::RemoveCommand),
+    ShellInit(shell_init::ShellInitCommand),
+    Status(status::StatusCommand),
+    Sync(sync::SyncCommand),
 }
 
 impl Args {
@@ -37,10 +57,16 @@ impl Args {
             Ops::Add(op) => op.run(workspace),
             Ops::Clone(op) => op.run(workspace),
             Ops::Completion(op) => op.run(workspace),
+            Ops::Foreach(op) => op.run(workspace),
             Ops::Import(op) => op.run(workspace),
             Ops::List(op) => op.run(workspace),
             Ops::New(op) => op.run(workspace),
+            Ops::Path(op) => op.run(workspace),
             Ops::Refresh(op) => op.run(workspace),
+            Ops::Remove(op) => op.run(workspace),
+            Ops::ShellInit(op) => op.run(workspace),
+            Ops::Status(op) => op.run(workspace),
+            Ops::Sync(op) => op.run(workspace),
         }
     }
 }
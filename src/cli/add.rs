@@ -1,11 +1,15 @@
 use crate::Workspace;
 use anyhow::Result;
+use clap_complete::engine::{ArgValueCompleter, PathCompleter};
 use std::{env, path::PathBuf};
 
 #[derive(Debug, clap::Parser)]
 #[command(name = "add", about = "Add existed repositories into management")]
 pub struct AddCommand {
-    #[arg(help = "Location of local repositories")]
+    #[arg(
+        help = "Location of local repositories",
+        add = ArgValueCompleter::new(PathCompleter::dir())
+    )]
     paths: Option<Vec<PathBuf>>,
 }
 
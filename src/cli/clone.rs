@@ -1,5 +1,6 @@
-use crate::{query::Query, vcs::Vcs, Remote, Workspace};
-use anyhow::Result;
+use crate::{query::Query, remote::Protocol, vcs::Vcs, Workspace};
+use anyhow::{anyhow, Result};
+use clap_complete::engine::ArgValueCandidates;
 use std::path::PathBuf;
 
 #[derive(Debug, clap::Parser)]
@@ -9,7 +10,10 @@ use std::path::PathBuf;
     about = "Clone remote repositories, and then add it under management"
 )]
 pub struct CloneCommand {
-    #[arg(help = "An URL or a string to determine the URL of remote repository")]
+    #[arg(
+        help = "An URL or a string to determine the URL of remote repository",
+        add = ArgValueCandidates::new(super::completion::repository_candidates)
+    )]
     query: Query,
 
     #[arg(help = "Destination directory of cloned repository")]
@@ -28,18 +32,53 @@ pub struct CloneCommand {
     )]
     ssh: bool,
 
-    #[arg(long = "vcs", help = "Used Version Control System", default_value_t = Vcs::Git)]
-    vcs: Vcs,
+    #[arg(
+        long = "https",
+        conflicts_with = "ssh",
+        help = "Use HTTP(s) protocol even if SSH is configured for the host"
+    )]
+    https: bool,
+
+    #[arg(
+        long = "vcs",
+        help = "Used Version Control System [default: git, or the one configured for the host]"
+    )]
+    vcs: Option<Vcs>,
 }
 
 impl CloneCommand {
+    fn protocol(&self) -> Option<Protocol> {
+        match (self.ssh, self.https) {
+            (true, _) => Some(Protocol::Ssh),
+            (_, true) => Some(Protocol::Https),
+            _ => None,
+        }
+    }
+
     pub fn run(self, workspace: &mut Workspace) -> Result<()> {
-        let remote = Remote::from_query(&self.query, self.ssh, workspace.default_host())?;
+        let remote = workspace.resolve_remote(&self.query, self.protocol())?;
+        let vcs = self
+            .vcs
+            .unwrap_or_else(|| workspace.default_vcs(&self.query));
         let dest = match self.dest {
             Some(dest) => dest,
-            None => workspace.resolve_query(&self.query, self.root.as_deref())?,
+            None => {
+                let dest = workspace.resolve_query(&self.query, self.root.as_deref())?;
+                // An explicit destination is honoured, to make another clone on purpose.
+                if let Some(repo) = workspace
+                    .find_by_remote(&remote)
+                    .find(|repo| repo.path() != dest)
+                {
+                    return Err(anyhow!(
+                        "{} has already been cloned into {} (specify the destination to clone it again)",
+                        remote.url(),
+                        repo.path_string()
+                    ));
+                }
+                dest
+            }
         };
-        workspace.clone_repository(remote, &dest, self.vcs)?;
+        workspace.clone_repository(remote, &dest, vcs)?;
 
         workspace.save_cache()?;
         Ok(())
@@ -1,8 +1,12 @@
-use crate::{cli::Args, Workspace};
+use crate::{cli::Args, Cache, Config, Workspace};
 use anyhow::Result;
 use clap::CommandFactory as _;
-use clap_complete::Shell;
-use std::path::PathBuf;
+use clap_complete::{
+    engine::CompletionCandidate,
+    env::{self, EnvCompleter},
+    Shell,
+};
+use std::{collections::BTreeSet, io::Write, path::PathBuf};
 
 #[derive(Debug, clap::Parser)]
 #[command(
@@ -16,15 +20,45 @@ pub struct CompletionCommand {
 
     #[arg(help = "Destination path to generated script")]
     out_file: Option<PathBuf>,
+
+    #[arg(
+        long = "dynamic",
+        help = "Generate a script which also completes managed repositories and hosts"
+    )]
+    dynamic: bool,
 }
 
 impl CompletionCommand {
     pub fn run(self, _: &mut Workspace) -> Result<()> {
+        if self.dynamic {
+            let mut out: Box<dyn Write> = match self.out_file {
+                Some(path) => Box::new(
+                    ::std::fs::OpenOptions::new()
+                        .write(true)
+                        .create(true)
+                        .truncate(true)
+                        .open(path)?,
+                ),
+                None => Box::new(std::io::stdout()),
+            };
+            let completer: &dyn EnvCompleter = match self.shell {
+                Shell::Bash => &env::Bash,
+                Shell::Elvish => &env::Elvish,
+                Shell::Fish => &env::Fish,
+                Shell::PowerShell => &env::Powershell,
+                Shell::Zsh => &env::Zsh,
+                shell => return Err(anyhow::anyhow!("{} is not supported", shell)),
+            };
+            let name = env!("CARGO_PKG_NAME");
+            completer.write_registration("COMPLETE", name, name, name, &mut out)?;
+            return Ok(());
+        }
+
         if let Some(path) = self.out_file {
             let mut file = ::std::fs::OpenOptions::new()
                 .write(true)
                 .create(true)
-                .append(false)
+                .truncate(true)
                 .open(path)
                 .unwrap();
             clap_complete::generate(
@@ -44,3 +78,54 @@ impl CompletionCommand {
         Ok(())
     }
 }
+
+/// Candidates of repository queries, read from the cache and configuration.
+///
+/// This is called by the dynamic completion, so any error results in no candidates.
+pub(crate) fn repository_candidates() -> Vec<CompletionCandidate> {
+    let config = match Config::new(None) {
+        Ok(config) => config,
+        Err(_) => return vec![],
+    };
+    let cache = match Cache::new(&config.cache_dir()) {
+        Ok(cache) => cache,
+        Err(_) => return vec![],
+    };
+
+    let mut names = BTreeSet::new();
+    let mut paths = BTreeSet::new();
+    let repos = cache.get_opt().map(|cache| cache.repositories.as_slice());
+    for repo in repos.into_iter().flatten() {
+        names.insert(repo.name().to_owned());
+        if let Some(remote) = repo.remote().filter(|remote| remote.host().is_some()) {
+            let path = remote.path().trim_matches('/');
+            paths.insert(path.strip_suffix(".git").unwrap_or(path).to_owned());
+        }
+    }
+
+    let mut hosts: BTreeSet<_> = config.hosts.keys().cloned().collect();
+    hosts.insert(config.host.clone());
+
+    let mut candidates = Vec::new();
+    candidates.extend(
+        names
+            .into_iter()
+            .map(|name| CompletionCandidate::new(name).help(Some("repository".into()))),
+    );
+    candidates.extend(
+        paths
+            .into_iter()
+            .map(|path| CompletionCandidate::new(path).help(Some("remote path".into()))),
+    );
+    candidates.extend(
+        hosts
+            .into_iter()
+            .map(|host| CompletionCandidate::new(format!("{}/", host)).help(Some("host".into()))),
+    );
+    candidates.extend(
+        config.aliases.keys().map(|alias| {
+            CompletionCandidate::new(format!("{}:", alias)).help(Some("alias".into()))
+        }),
+    );
+    candidates
+}
@@ -0,0 +1,97 @@
+use crate::{Filter, Workspace};
+use anyhow::{anyhow, Result};
+use std::io::{self, Write as _};
+
+#[derive(Debug, clap::Parser)]
+#[command(
+    name = "foreach",
+    about = "Execute a command in every managed repository"
+)]
+pub struct ForeachCommand {
+    #[command(flatten)]
+    filter: Filter,
+
+    #[arg(
+        short = 'k',
+        long = "keep-going",
+        help = "Continue with the remaining repositories after a command fails"
+    )]
+    keep_going: bool,
+
+    #[arg(last = true, required = true, help = "Command and its arguments")]
+    command: Vec<String>,
+}
+
+impl ForeachCommand {
+    pub fn run(self, workspace: &mut Workspace) -> Result<()> {
+        let (command, args) = self.command.split_first().expect("required argument");
+
+        let repos = workspace
+            .repositories()
+            .into_iter()
+            .flatten()
+            .filter(|repo| self.filter.matches(repo))
+            .collect();
+
+        // The output is captured only when the commands run concurrently,
+        // so that sequential runs can still interact with the terminal.
+        let pool = workspace.pool();
+        let capture = pool.jobs() > 1;
+
+        let mut succeeded = 0;
+        let mut failed = Vec::new();
+        pool.for_each(
+            repos,
+            |repo| {
+                let header = format!("==> {} ({})", repo.name(), repo.path_string());
+                if capture {
+                    let result = repo
+                        .run_command_captured(command, args)
+                        .map(|output| (output.status.success(), Some(output)));
+                    (repo, header, result)
+                } else {
+                    println!("{}", header);
+                    let result = repo
+                        .run_command(command, args)
+                        .map(|success| (success, None));
+                    (repo, header, result)
+                }
+            },
+            |(repo, header, result)| {
+                let success = match result {
+                    Ok((success, output)) => {
+                        if let Some(output) = output {
+                            let stdout = io::stdout();
+                            let mut stdout = stdout.lock();
+                            let _ = writeln!(stdout, "{}", header);
+                            let _ = stdout.write_all(&output.stdout);
+                            let _ = io::stderr().write_all(&output.stderr);
+                        }
+                        success
+                    }
+                    Err(e) => {
+                        if capture {
+                            println!("{}", header);
+                        }
+                        eprintln!("failed to execute '{}': {}", command, e);
+                        false
+                    }
+                };
+                if success {
+                    succeeded += 1;
+                } else {
+                    failed.push(repo.name().to_owned());
+                }
+                success || self.keep_going
+            },
+        );
+
+        println!();
+        println!("{} succeeded, {} failed", succeeded, failed.len());
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("command failed in: {}", failed.join(", ")))
+        }
+    }
+}
@@ -9,7 +9,8 @@ use std::path::PathBuf;
     about = "Import existed repositories into management"
 )]
 pub struct ImportCommand {
-    #[arg(help = "Root directories contains for scanning")]
+    #[arg(help = "Root directories contains for scanning \
+                [default: includes and the root directories of hosts]")]
     roots: Option<Vec<PathBuf>>,
 
     #[arg(
@@ -21,9 +22,7 @@ pub struct ImportCommand {
 
 impl ImportCommand {
     pub fn run(self, workspace: &mut Workspace) -> Result<()> {
-        let roots = self
-            .roots
-            .unwrap_or_else(|| workspace.config.include_dirs.clone());
+        let roots = self.roots.unwrap_or_else(|| workspace.config.scan_dirs());
         for root in roots {
             workspace.import_repositories(root, self.depth)?;
         }
@@ -1,31 +1,182 @@
-use crate::Workspace;
-use anyhow::Result;
-use std::{fmt, str::FromStr};
+use crate::{config::ConfigData, Filter, Repository, SortKey, Workspace};
+use anyhow::{anyhow, Result};
+use std::{
+    io::{self, Write},
+    str::FromStr,
+};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, clap::ValueEnum)]
 enum ListFormat {
     Name,
+    #[value(name = "fullpath")]
     FullPath,
+    Relative,
+    HostOwnerRepo,
+    OwnerRepo,
+    Json,
+    Ndjson,
+    Tsv,
 }
 
-impl FromStr for ListFormat {
-    type Err = anyhow::Error;
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Name,
+    Path,
+    RelPath,
+    HostPath,
+    RepoPath,
+    Vcs,
+    Remote,
+    Host,
+    Owner,
+    Head,
+    DefaultBranch,
+    Description,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "name" => Ok(ListFormat::Name),
-            "fullpath" => Ok(ListFormat::FullPath),
-            _ => Err(anyhow::anyhow!("invalid list format")),
+impl Field {
+    /// Returns whether the value is recorded on add, import and refresh (see `Repository::load_metadata`).
+    fn needs_metadata(self) -> bool {
+        matches!(self, Field::Head | Field::DefaultBranch)
+    }
+
+    fn value(self, repo: &Repository, config: &ConfigData) -> String {
+        let remote = repo.remote();
+        match self {
+            Field::Name => repo.name().to_owned(),
+            Field::Path => repo.path_string(),
+            Field::RelPath => config
+                .root_dir_of(repo.path())
+                .and_then(|root| repo.path().strip_prefix(root).ok())
+                .map_or_else(|| repo.path_string(), |path| path.display().to_string()),
+            Field::HostPath => scoped_path(repo, config, true),
+            Field::RepoPath => scoped_path(repo, config, false),
+            Field::Vcs => repo.vcs().to_string(),
+            Field::Remote => remote.map_or("", |remote| remote.url()).to_owned(),
+            Field::Host => remote
+                .and_then(|remote| remote.host())
+                .unwrap_or("")
+                .to_owned(),
+            Field::Owner => remote
+                .and_then(|remote| remote.owner())
+                .unwrap_or("")
+                .to_owned(),
+            Field::Head => repo.head().unwrap_or("").to_owned(),
+            Field::DefaultBranch => repo.default_branch().unwrap_or("").to_owned(),
+            Field::Description => repo.description().unwrap_or("").to_owned(),
         }
     }
 }
 
-impl fmt::Display for ListFormat {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Name => f.write_str("name"),
-            Self::FullPath => f.write_str("fullpath"),
+/// Returns `[host/]owner/repo` derived from the remote, or the full path
+/// if the repository has no remote or lives outside of the root directories.
+fn scoped_path(repo: &Repository, config: &ConfigData, with_host: bool) -> String {
+    let remote = match repo.remote() {
+        Some(remote) if config.root_dir_of(repo.path()).is_some() => remote,
+        _ => return repo.path_string(),
+    };
+    match (remote.host(), remote.repo_path()) {
+        (Some(host), Some(path)) if with_host => format!("{}/{}", host, path),
+        (Some(_), Some(path)) => path.to_owned(),
+        _ => repo.path_string(),
+    }
+}
+
+/// Escape the characters which break the structure of TSV, as `\t`, `\n` and so on.
+fn escape_tsv(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Field(Field),
+}
+
+/// Output template, e.g. `{name}\t{remote}\t{path}`.
+#[derive(Debug, Clone)]
+struct Template(Vec<Segment>);
+
+impl Template {
+    fn needs_metadata(&self) -> bool {
+        self.0
+            .iter()
+            .any(|segment| matches!(segment, Segment::Field(field) if field.needs_metadata()))
+    }
+
+    fn render(&self, repo: &Repository, config: &ConfigData) -> String {
+        self.0
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(s) => s.clone(),
+                Segment::Field(field) => field.value(repo, config),
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Template {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some('0') => literal.push('\0'),
+                    Some(c) => literal.push(c),
+                    None => literal.push('\\'),
+                },
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(anyhow!("unclosed placeholder {{{}", name)),
+                        }
+                    }
+                    let field = match name.as_str() {
+                        "name" => Field::Name,
+                        "path" => Field::Path,
+                        "relpath" => Field::RelPath,
+                        "hostpath" => Field::HostPath,
+                        "repopath" => Field::RepoPath,
+                        "vcs" => Field::Vcs,
+                        "remote" => Field::Remote,
+                        "host" => Field::Host,
+                        "owner" => Field::Owner,
+                        "head" => Field::Head,
+                        "default_branch" => Field::DefaultBranch,
+                        "description" => Field::Description,
+                        name => return Err(anyhow!("unknown placeholder {{{}}}", name)),
+                    };
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field(field));
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
         }
+        Ok(Template(segments))
     }
 }
 
@@ -36,19 +187,161 @@ impl fmt::Display for ListFormat {
     about = "List local repositories managed by rhq"
 )]
 pub struct ListCommand {
-    #[arg(long = "format", help = "List format", default_value_t = ListFormat::FullPath)]
+    #[arg(
+        long = "format",
+        value_enum,
+        help = "List format",
+        default_value_t = ListFormat::FullPath
+    )]
     format: ListFormat,
+
+    #[arg(
+        long = "template",
+        conflicts_with = "format",
+        help = "Output template with placeholders: {name}, {path}, {relpath}, {hostpath}, {repopath}, {vcs}, {remote}, {host}, {owner}, {head}, {default_branch} and {description}"
+    )]
+    template: Option<Template>,
+
+    #[arg(
+        short = '0',
+        long = "null",
+        help = "Separate entries by NUL characters instead of newlines"
+    )]
+    null: bool,
+
+    #[arg(
+        long = "sort",
+        value_enum,
+        help = "Sort the output by the key instead of the stored order"
+    )]
+    sort: Option<SortKey>,
+
+    #[arg(long = "reverse", help = "Reverse the order of the output")]
+    reverse: bool,
+
+    #[command(flatten)]
+    filter: Filter,
 }
 
 impl ListCommand {
+    fn needs_metadata(&self) -> bool {
+        let by_format = match self.template {
+            Some(ref template) => template.needs_metadata(),
+            None => matches!(self.format, ListFormat::Json | ListFormat::Ndjson),
+        };
+        by_format || self.sort == Some(SortKey::LastCommit)
+    }
+
     pub fn run(self, workspace: &mut Workspace) -> Result<()> {
-        for repo in workspace.repositories().into_iter().flatten() {
-            match self.format {
-                ListFormat::Name => println!("{}", repo.name()),
-                ListFormat::FullPath => println!("{}", repo.path_string()),
+        let mut repos: Vec<Repository> = workspace
+            .repositories()
+            .into_iter()
+            .flatten()
+            .filter(|repo| self.filter.matches(repo))
+            .cloned()
+            .collect();
+        // The entries of older caches have never been inspected.
+        if self.needs_metadata() {
+            repos = workspace.pool().map(repos, |mut repo| {
+                if repo.head().is_none() {
+                    repo.load_metadata();
+                }
+                repo
+            });
+        }
+        match self.sort {
+            Some(key) => key.sort(&mut repos, self.reverse),
+            None if self.reverse => repos.reverse(),
+            None => {}
+        }
+        let config = &**workspace.config;
+        let terminator = if self.null { "\0" } else { "\n" };
+
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+
+        if let Some(ref template) = self.template {
+            for repo in &repos {
+                write!(out, "{}{}", template.render(repo, config), terminator)?;
+            }
+            return Ok(());
+        }
+
+        match self.format {
+            ListFormat::Json => {
+                serde_json::to_writer_pretty(&mut out, &repos)?;
+                writeln!(out)?;
+            }
+            ListFormat::Ndjson => {
+                for repo in &repos {
+                    serde_json::to_writer(&mut out, repo)?;
+                    write!(out, "{}", terminator)?;
+                }
+            }
+            ListFormat::Tsv => {
+                const COLUMNS: [Field; 4] = [Field::Name, Field::Vcs, Field::Remote, Field::Path];
+                for repo in &repos {
+                    let row: Vec<String> = COLUMNS
+                        .iter()
+                        .map(|field| escape_tsv(&field.value(repo, config)))
+                        .collect();
+                    write!(out, "{}{}", row.join("\t"), terminator)?;
+                }
+            }
+            ListFormat::Relative => {
+                for repo in &repos {
+                    write!(out, "{}{}", Field::RelPath.value(repo, config), terminator)?;
+                }
+            }
+            ListFormat::HostOwnerRepo => {
+                for repo in &repos {
+                    write!(out, "{}{}", Field::HostPath.value(repo, config), terminator)?;
+                }
+            }
+            ListFormat::OwnerRepo => {
+                for repo in &repos {
+                    write!(out, "{}{}", Field::RepoPath.value(repo, config), terminator)?;
+                }
+            }
+            ListFormat::Name => {
+                for repo in &repos {
+                    write!(out, "{}{}", repo.name(), terminator)?;
+                }
+            }
+            ListFormat::FullPath => {
+                for repo in &repos {
+                    write!(out, "{}{}", repo.path_string(), terminator)?;
+                }
             }
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_template() {
+        let template: Template = "{name}\\t{path}".parse().unwrap();
+        assert_eq!(
+            template.0,
+            [
+                Segment::Field(Field::Name),
+                Segment::Literal("\t".to_owned()),
+                Segment::Field(Field::Path),
+            ]
+        );
+        assert!("{name".parse::<Template>().is_err());
+        assert!("{name}{path".parse::<Template>().is_err());
+        assert!("{unknown}".parse::<Template>().is_err());
+    }
+
+    #[test]
+    fn escape_tsv_fields() {
+        assert_eq!(escape_tsv("a\tb\nc\\d"), "a\\tb\\nc\\\\d");
+        assert_eq!(escape_tsv("plain"), "plain");
+    }
+}
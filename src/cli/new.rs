@@ -1,5 +1,6 @@
-use crate::{query::Query, vcs::Vcs, Workspace};
+use crate::{query::Query, remote::Protocol, vcs::Vcs, Workspace};
 use anyhow::Result;
+use clap_complete::engine::ArgValueCandidates;
 use std::path::PathBuf;
 
 #[derive(Debug, clap::Parser)]
@@ -8,7 +9,10 @@ use std::path::PathBuf;
     about = "Create a new repository and add it into management"
 )]
 pub struct NewCommand {
-    #[arg(help = "Path of target repository, or URL-like pattern")]
+    #[arg(
+        help = "Path of target repository, or URL-like pattern",
+        add = ArgValueCandidates::new(super::completion::repository_candidates)
+    )]
     query: Query,
 
     #[arg(
@@ -17,8 +21,11 @@ pub struct NewCommand {
     )]
     root: Option<PathBuf>,
 
-    #[arg(long = "vcs", help = "Used Version Control System", default_value_t = Vcs::Git)]
-    vcs: Vcs,
+    #[arg(
+        long = "vcs",
+        help = "Used Version Control System [default: git, or the one configured for the host]"
+    )]
+    vcs: Option<Vcs>,
 
     #[arg(
         short = 's',
@@ -26,11 +33,31 @@ pub struct NewCommand {
         help = "Use SSH protocol instead of HTTP(s)"
     )]
     ssh: bool,
+
+    #[arg(
+        long = "https",
+        conflicts_with = "ssh",
+        help = "Use HTTP(s) protocol even if SSH is configured for the host"
+    )]
+    https: bool,
 }
 
 impl NewCommand {
+    fn protocol(&self) -> Option<Protocol> {
+        match (self.ssh, self.https) {
+            (true, _) => Some(Protocol::Ssh),
+            (_, true) => Some(Protocol::Https),
+            _ => None,
+        }
+    }
+
     pub fn run(self, workspace: &mut Workspace) -> Result<()> {
-        workspace.create_repository(&self.query, self.vcs, self.ssh, self.root.as_deref())?;
+        workspace.create_repository(
+            &self.query,
+            self.vcs,
+            self.protocol(),
+            self.root.as_deref(),
+        )?;
         workspace.save_cache()?;
         Ok(())
     }
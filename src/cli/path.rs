@@ -0,0 +1,114 @@
+use crate::{query::Query, Workspace};
+use anyhow::{anyhow, Result};
+use clap_complete::engine::ArgValueCandidates;
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Parser)]
+#[command(
+    name = "path",
+    about = "Print the local path of a repository without cloning it"
+)]
+pub struct PathCommand {
+    #[arg(
+        help = "Query, local path, name or pattern of the repository",
+        add = ArgValueCandidates::new(super::completion::repository_candidates)
+    )]
+    query: String,
+
+    #[arg(long = "all", help = "Print all matched repositories")]
+    all: bool,
+
+    // Used by the `rhq cd` wrapper of `shell-init`, so that only `rhq cd` counts as an access.
+    #[arg(
+        long = "touch",
+        hide = true,
+        help = "Record the access time of the found repositories"
+    )]
+    touch: bool,
+}
+
+impl PathCommand {
+    pub fn run(self, workspace: &mut Workspace) -> Result<()> {
+        let mut found = self.find_exact(workspace)?;
+        if found.is_empty() {
+            // The location derived from the query takes precedence over the pattern,
+            // as long as it exists.
+            let resolved = self
+                .query
+                .parse::<Query>()
+                .and_then(|query| workspace.resolve_query(&query, None));
+            if let Ok(ref path) = resolved {
+                if path.exists() {
+                    println!("{}", path.display());
+                    return Ok(());
+                }
+            }
+
+            found = self.find_by_pattern(workspace)?;
+            if found.is_empty() {
+                let path = resolved?;
+                println!("{}", path.display());
+                return Err(anyhow!("{} does not exist", path.display()));
+            }
+        }
+
+        if self.touch {
+            for path in &found {
+                workspace.touch_repository(path);
+            }
+            // The access time is informative, so it is not worth waiting for the lock.
+            match workspace.try_save_cache() {
+                Ok(true) => {}
+                Ok(false) => log::debug!("the cache is locked; the access time is not recorded"),
+                Err(err) => log::warn!("failed to record the access time: {}", err),
+            }
+        }
+
+        for path in &found {
+            println!("{}", path.display());
+        }
+
+        if found.iter().any(|path| path.exists()) {
+            Ok(())
+        } else {
+            Err(anyhow!("{} does not exist", found[0].display()))
+        }
+    }
+
+    /// Find the managed repositories by their location or name.
+    fn find_exact(&self, workspace: &Workspace) -> Result<Vec<PathBuf>> {
+        let found = if self.all {
+            workspace.find_repositories(&self.query)
+        } else {
+            workspace
+                .find_repository(&self.query)?
+                .into_iter()
+                .collect()
+        };
+        Ok(found
+            .into_iter()
+            .map(|repo| repo.path().to_owned())
+            .collect())
+    }
+
+    /// Find the managed repositories whose name or path matches the query as a pattern.
+    fn find_by_pattern(&self, workspace: &Workspace) -> Result<Vec<PathBuf>> {
+        let found: Vec<PathBuf> = workspace
+            .find_by_pattern(&self.query)
+            .into_iter()
+            .map(|repo| repo.path().to_owned())
+            .collect();
+        if found.len() > 1 && !self.all {
+            let paths: Vec<String> = found
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            return Err(anyhow!(
+                "'{}' is ambiguous; candidates are:\n  {}",
+                self.query,
+                paths.join("\n  ")
+            ));
+        }
+        Ok(found)
+    }
+}
@@ -1,4 +1,4 @@
-use crate::Workspace;
+use crate::{SortKey, Workspace};
 use anyhow::Result;
 
 #[derive(Debug, clap::Parser)]
@@ -7,15 +7,25 @@ use anyhow::Result;
     about = "Scan repository list and drop if it is not existed or matches exclude pattern."
 )]
 pub struct RefreshCommand {
-    #[arg(short = 's', long = "sort", help = "Sort by path string")]
-    sort: bool,
+    #[arg(
+        short = 's',
+        long = "sort",
+        num_args = 0..=1,
+        default_missing_value = "name",
+        value_enum,
+        help = "Sort the repository list by the key and save the order"
+    )]
+    sort: Option<SortKey>,
+
+    #[arg(long = "reverse", requires = "sort", help = "Reverse the sort order")]
+    reverse: bool,
 }
 
 impl RefreshCommand {
     pub fn run(self, workspace: &mut Workspace) -> Result<()> {
         workspace.drop_invalid_repositories();
-        if self.sort {
-            workspace.sort_repositories();
+        if let Some(key) = self.sort {
+            workspace.sort_repositories(key, self.reverse);
         }
         workspace.save_cache()?;
         Ok(())
@@ -0,0 +1,62 @@
+use crate::Workspace;
+use anyhow::{anyhow, Result};
+use clap_complete::engine::ArgValueCandidates;
+
+#[derive(Debug, clap::Parser)]
+#[command(
+    name = "remove",
+    aliases = ["rm"],
+    about = "Remove a repository from management"
+)]
+pub struct RemoveCommand {
+    #[arg(
+        help = "Local path, query or name of the managed repository",
+        add = ArgValueCandidates::new(super::completion::repository_candidates)
+    )]
+    target: String,
+
+    #[arg(
+        long = "trash",
+        conflicts_with = "purge",
+        help = "Move the working tree into the trash directory"
+    )]
+    trash: bool,
+
+    #[arg(long = "purge", help = "Delete the working tree")]
+    purge: bool,
+
+    #[arg(
+        short = 'f',
+        long = "force",
+        help = "Remove the working tree even if it has uncommitted changes"
+    )]
+    force: bool,
+}
+
+impl RemoveCommand {
+    pub fn run(self, workspace: &mut Workspace) -> Result<()> {
+        let repo = workspace
+            .find_repository(&self.target)?
+            .cloned()
+            .ok_or_else(|| anyhow!("{} is not a managed repository", self.target))?;
+
+        if (self.trash || self.purge) && repo.path().exists() {
+            if !self.force && repo.vcs().is_dirty(repo.path())? {
+                return Err(anyhow!(
+                    "{} has uncommitted changes (use --force to remove anyway)",
+                    repo.path_string()
+                ));
+            }
+            if self.trash {
+                workspace.trash_repository(&repo)?;
+            } else {
+                workspace.purge_repository(&repo)?;
+            }
+        }
+
+        workspace.remove_repository(repo.path());
+        workspace.save_cache()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use crate::Workspace;
+use anyhow::{anyhow, Result};
+use clap_complete::Shell;
+
+const POSIX_SCRIPT: &str = r#"rhq() {
+    if [ "$1" = "cd" ]; then
+        shift
+        local __rhq_dir
+        __rhq_dir="$(command rhq path --touch -- "$@")" || return
+        cd -- "$__rhq_dir"
+    else
+        command rhq "$@"
+    fi
+}
+"#;
+
+const FISH_SCRIPT: &str = r#"function rhq
+    if test (count $argv) -gt 0; and test "$argv[1]" = cd
+        set -l __rhq_dir (command rhq path --touch -- $argv[2..-1]); or return
+        cd $__rhq_dir
+    else
+        command rhq $argv
+    end
+end
+"#;
+
+const POWERSHELL_SCRIPT: &str = r#"function rhq {
+    $__rhq = Get-Command rhq -CommandType Application | Select-Object -First 1
+    if ($args.Count -gt 0 -and $args[0] -eq 'cd') {
+        $__rhq_dir = & $__rhq path --touch -- @($args | Select-Object -Skip 1)
+        if ($LASTEXITCODE -eq 0) { Set-Location -LiteralPath $__rhq_dir }
+    } else {
+        & $__rhq @args
+    }
+}
+"#;
+
+#[derive(Debug, clap::Parser)]
+#[command(
+    name = "shell-init",
+    about = "Print a shell function providing `rhq cd <query>`",
+    long_about = "Print a shell function providing `rhq cd <query>`.\n\n\
+                  The query is resolved as `rhq path`, so it may also be a glob pattern \
+                  or a part of the path of a managed repository.",
+    after_help = "\
+Add one of the following lines to your shell configuration:
+  bash:       eval \"$(rhq shell-init bash)\"
+  zsh:        eval \"$(rhq shell-init zsh)\"
+  fish:       rhq shell-init fish | source
+  PowerShell: Invoke-Expression (& rhq shell-init powershell | Out-String)"
+)]
+pub struct ShellInitCommand {
+    #[arg(help = "Target shell")]
+    shell: Shell,
+}
+
+impl ShellInitCommand {
+    pub fn run(self, _: &mut Workspace) -> Result<()> {
+        let script = match self.shell {
+            Shell::Bash | Shell::Zsh => POSIX_SCRIPT,
+            Shell::Fish => FISH_SCRIPT,
+            Shell::PowerShell => POWERSHELL_SCRIPT,
+            shell => return Err(anyhow!("{} is not supported", shell)),
+        };
+        print!("{}", script);
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{vcs::Status, Filter, Repository, Workspace};
+use anyhow::Result;
+
+#[derive(Debug, clap::Parser)]
+#[command(
+    name = "status",
+    aliases = ["st"],
+    about = "Show the working tree status of managed repositories"
+)]
+pub struct StatusCommand {
+    #[arg(
+        long = "only-dirty",
+        help = "Show only repositories which have uncommitted changes"
+    )]
+    only_dirty: bool,
+
+    #[command(flatten)]
+    filter: Filter,
+}
+
+impl StatusCommand {
+    pub fn run(self, workspace: &mut Workspace) -> Result<()> {
+        let mut rows = vec![[
+            "NAME".to_owned(),
+            "BRANCH".to_owned(),
+            "STATE".to_owned(),
+            "UPSTREAM".to_owned(),
+            "PATH".to_owned(),
+        ]];
+        let repos: Vec<&Repository> = workspace
+            .repositories()
+            .into_iter()
+            .flatten()
+            .filter(|repo| self.filter.matches(repo))
+            .collect();
+        let statuses = workspace.pool().map(repos.clone(), |repo| {
+            if repo.path().exists() {
+                repo.vcs().status(repo.path())
+            } else {
+                Err(anyhow::anyhow!("missing"))
+            }
+        });
+        for (repo, status) in repos.into_iter().zip(statuses) {
+            if self.only_dirty && !status.as_ref().map_or(true, Status::is_dirty) {
+                continue;
+            }
+            rows.push(make_row(repo, status));
+        }
+
+        let mut widths = [0; 4];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        for [name, branch, state, upstream, path] in &rows {
+            println!(
+                "{:<w0$}  {:<w1$}  {:<w2$}  {:<w3$}  {}",
+                name,
+                branch,
+                state,
+                upstream,
+                path,
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2],
+                w3 = widths[3],
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn make_row(repo: &Repository, status: Result<Status>) -> [String; 5] {
+    let (branch, state, upstream) = match status {
+        Ok(status) => {
+            let branch = status.branch.clone().unwrap_or_else(|| "-".to_owned());
+            let state = match (status.modified, status.untracked) {
+                (0, 0) => "clean".to_owned(),
+                (modified, 0) => format!("{}M", modified),
+                (0, untracked) => format!("{}?", untracked),
+                (modified, untracked) => format!("{}M {}?", modified, untracked),
+            };
+            let upstream = match (status.ahead, status.behind) {
+                (Some(0), Some(0)) => "=".to_owned(),
+                (Some(ahead), Some(0)) => format!("+{}", ahead),
+                (Some(0), Some(behind)) => format!("-{}", behind),
+                (Some(ahead), Some(behind)) => format!("+{}/-{}", ahead, behind),
+                (Some(ahead), None) => format!("+{}/?", ahead),
+                (None, Some(behind)) => format!("?/-{}", behind),
+                (None, None) => "-".to_owned(),
+            };
+            (branch, state, upstream)
+        }
+        Err(e) => ("-".to_owned(), format!("error: {}", e), "-".to_owned()),
+    };
+    [
+        repo.name().to_owned(),
+        branch,
+        state,
+        upstream,
+        repo.path_string(),
+    ]
+}
@@ -0,0 +1,110 @@
+use crate::{vcs::Unsupported, Filter, Repository, Workspace};
+use anyhow::{anyhow, Result};
+
+#[derive(Debug, clap::Parser)]
+#[command(
+    name = "sync",
+    aliases = ["update"],
+    about = "Fetch and fast-forward managed repositories"
+)]
+pub struct SyncCommand {
+    #[command(flatten)]
+    filter: Filter,
+}
+
+/// Result of synchronizing a repository.
+enum Outcome {
+    Updated,
+    UpToDate,
+    Skipped(&'static str),
+    Failed(anyhow::Error),
+}
+
+impl SyncCommand {
+    pub fn run(self, workspace: &mut Workspace) -> Result<()> {
+        let repos = workspace
+            .repositories()
+            .into_iter()
+            .flatten()
+            .filter(|repo| self.filter.matches(repo))
+            .collect();
+
+        let (mut updated, mut skipped, mut failed) = (0, 0, 0);
+        workspace.pool().for_each(
+            repos,
+            |repo| (repo, sync_repository(repo)),
+            |(repo, outcome)| {
+                match outcome {
+                    Outcome::Updated => {
+                        updated += 1;
+                        println!("{}: updated", repo.name());
+                    }
+                    Outcome::UpToDate => println!("{}: up to date", repo.name()),
+                    Outcome::Skipped(reason) => {
+                        skipped += 1;
+                        println!("{}: skipped ({})", repo.name(), reason);
+                    }
+                    Outcome::Failed(e) => {
+                        failed += 1;
+                        println!("{}: failed ({})", repo.name(), e);
+                    }
+                }
+                true
+            },
+        );
+
+        println!();
+        println!(
+            "{} updated, {} skipped, {} failed",
+            updated, skipped, failed
+        );
+        if failed == 0 {
+            Ok(())
+        } else {
+            Err(anyhow!("failed to synchronize {} repositories", failed))
+        }
+    }
+}
+
+fn sync_repository(repo: &Repository) -> Outcome {
+    if repo.remote().is_none() {
+        return Outcome::Skipped("no remote");
+    }
+    if !repo.path().exists() {
+        return Outcome::Skipped("missing");
+    }
+
+    let vcs = repo.vcs();
+    match vcs.status(repo.path()) {
+        Ok(status) if status.is_dirty() => return Outcome::Skipped("uncommitted changes"),
+        Ok(status) if status.has_upstream == Some(false) => return Outcome::Skipped("no upstream"),
+        Ok(_) => {}
+        Err(e) => return Outcome::Failed(e),
+    }
+
+    if let Err(e) = vcs.do_fetch(repo.path()) {
+        if e.is::<Unsupported>() {
+            return Outcome::Skipped("fetch is not supported");
+        }
+        return Outcome::Failed(e);
+    }
+
+    // When the VCS cannot tell the number of incoming commits,
+    // leave the decision to `do_pull`, which never creates a merge.
+    match vcs.status(repo.path()) {
+        Ok(status) => match (status.ahead, status.behind) {
+            (_, Some(0)) => return Outcome::UpToDate,
+            (Some(ahead), Some(_)) if ahead > 0 => return Outcome::Skipped("diverged"),
+            _ => {}
+        },
+        Err(e) => return Outcome::Failed(e),
+    }
+
+    // The backends which cannot update the working tree without merging
+    // (e.g. jj) only fetch the changes.
+    match vcs.do_pull(repo.path()) {
+        Ok(()) => Outcome::Updated,
+        Err(e) if e.is::<Unsupported>() => Outcome::Skipped("pull is not supported"),
+        Err(e) => Outcome::Failed(e),
+    }
+}
@@ -1,9 +1,16 @@
 //! Defines configuration file format.
 
-use anyhow::{anyhow, Result};
+use crate::{
+    layout::Layout,
+    query::{Alias, Query},
+    remote::Protocol,
+    vcs::Vcs,
+};
+use anyhow::{anyhow, Context as _, Result};
 use glob::Pattern;
 use serde::Deserialize;
 use std::{
+    collections::HashMap,
     fs,
     io::Read,
     ops::{Deref, DerefMut},
@@ -24,6 +31,51 @@ struct RawConfigData {
     default_host: Option<String>,
     includes: Option<Vec<String>>,
     excludes: Option<Vec<String>>,
+    jobs: Option<usize>,
+    layout: Option<String>,
+    hosts: Option<HashMap<String, RawHostConfig>>,
+    aliases: Option<HashMap<String, String>>,
+}
+
+/// host-specific configuration load from config files
+#[derive(Deserialize)]
+struct RawHostConfig {
+    protocol: Option<Protocol>,
+    user: Option<String>,
+    vcs: Option<String>,
+    root: Option<String>,
+    layout: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct HostConfig {
+    pub protocol: Option<Protocol>,
+    pub username: Option<String>,
+    pub vcs: Option<Vcs>,
+    pub root_dir: Option<PathBuf>,
+    pub layout: Option<Layout>,
+}
+
+impl HostConfig {
+    fn from_raw(host: &str, raw: RawHostConfig) -> Result<Self> {
+        let layout = raw
+            .layout
+            .map(|layout| layout.parse())
+            .transpose()
+            .with_context(|| format!("invalid layout for host '{}'", host))?;
+        let vcs = raw
+            .vcs
+            .map(|vcs| vcs.parse().map_err(|e| anyhow!("{} (host = {})", e, host)))
+            .transpose()?;
+        let root_dir = raw.root.map(crate::util::make_path_buf).transpose()?;
+        Ok(Self {
+            protocol: raw.protocol,
+            username: raw.user,
+            vcs,
+            root_dir,
+            layout,
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -32,6 +84,10 @@ pub struct ConfigData {
     pub host: String,
     pub include_dirs: Vec<PathBuf>,
     pub exclude_patterns: Vec<Pattern>,
+    pub jobs: usize,
+    pub layout: Layout,
+    pub hosts: HashMap<String, HostConfig>,
+    pub aliases: HashMap<String, Alias>,
 }
 
 impl ConfigData {
@@ -44,7 +100,7 @@ impl ConfigData {
             .as_deref()
             .unwrap_or(&[])
             .iter()
-            .filter_map(|root| crate::util::make_path_buf(&root).ok())
+            .filter_map(|root| crate::util::make_path_buf(root).ok())
             .collect();
 
         let exclude_patterns = raw
@@ -62,13 +118,99 @@ impl ConfigData {
 
         let host = raw.default_host.unwrap_or_else(|| "github.com".to_owned());
 
+        let jobs = raw.jobs.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(Into::into)
+                .unwrap_or(1)
+        });
+
+        let layout = match raw.layout {
+            Some(layout) => layout.parse()?,
+            None => Layout::default(),
+        };
+
+        let hosts = raw
+            .hosts
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(host, raw)| HostConfig::from_raw(&host, raw).map(|config| (host, config)))
+            .collect::<Result<_>>()?;
+
+        let aliases = raw
+            .aliases
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, alias)| {
+                Alias::validate_name(&name)?;
+                Ok((name, alias.parse()?))
+            })
+            .collect::<Result<_>>()?;
+
         Ok(Self {
             root_dir,
             host,
             include_dirs,
             exclude_patterns,
+            jobs,
+            layout,
+            hosts,
+            aliases,
         })
     }
+
+    /// Returns the directory layout used for repositories on `host`.
+    pub fn layout_for(&self, host: &str) -> &Layout {
+        self.hosts
+            .get(host)
+            .and_then(|config| config.layout.as_ref())
+            .unwrap_or(&self.layout)
+    }
+
+    /// Returns the root directory of repositories on `host`.
+    pub fn root_dir_for(&self, host: &str) -> &Path {
+        self.hosts
+            .get(host)
+            .and_then(|config| config.root_dir.as_deref())
+            .unwrap_or(&self.root_dir)
+    }
+
+    /// Returns the directories scanned by `import` by default,
+    /// i.e. `includes` and the root directories configured for hosts.
+    pub fn scan_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = self.include_dirs.clone();
+        dirs.extend(
+            self.hosts
+                .values()
+                .filter_map(|config| config.root_dir.clone()),
+        );
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+
+    /// Returns the root directory which contains `path`, if any.
+    ///
+    /// When the root directories are nested, the innermost one is chosen.
+    pub fn root_dir_of(&self, path: &Path) -> Option<&Path> {
+        std::iter::once(&*self.root_dir)
+            .chain(
+                self.hosts
+                    .values()
+                    .filter_map(|config| config.root_dir.as_deref()),
+            )
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+    }
+
+    /// Expand the host alias in `query`, or returns `None` if it does not use any alias.
+    pub fn expand_alias(&self, query: &Query) -> Option<Query> {
+        query.expand_alias(&self.aliases)
+    }
+
+    /// Returns the host-specific settings, if configured.
+    pub fn host_config(&self, host: &str) -> Option<&HostConfig> {
+        self.hosts.get(host)
+    }
 }
 
 #[derive(Debug)]
@@ -105,6 +247,11 @@ impl Config {
     pub fn cache_dir(&self) -> PathBuf {
         self.root_dir.join(".cache.json")
     }
+
+    /// Returns the directory where removed repositories are moved into.
+    pub fn trash_dir(&self) -> PathBuf {
+        self.root_dir.join(".trash")
+    }
 }
 
 impl Deref for Config {
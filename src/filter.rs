@@ -0,0 +1,164 @@
+//! Selection of repositories shared by commands operating on multiple repositories.
+
+use crate::{repository::Repository, vcs::Vcs};
+use glob::Pattern;
+use regex::Regex;
+
+/// Conditions to select managed repositories.
+///
+/// A repository is selected only if it satisfies all of the specified conditions.
+#[derive(Debug, Default, Clone, clap::Args)]
+#[command(about = None, long_about = None)]
+pub struct Filter {
+    #[arg(help = "Select repositories whose path contains this string (case-insensitive)")]
+    pub pattern: Option<String>,
+
+    #[arg(long = "vcs", help = "Select repositories managed by this VCS")]
+    pub vcs: Vec<Vcs>,
+
+    #[arg(
+        long = "host",
+        help = "Select repositories whose remote is on this host"
+    )]
+    pub host: Vec<String>,
+
+    #[arg(
+        long = "owner",
+        help = "Select repositories whose remote is owned by this user or group"
+    )]
+    pub owner: Vec<String>,
+
+    #[arg(
+        long = "match",
+        alias = "filter",
+        help = "Glob pattern to select repositories by their name or path"
+    )]
+    pub glob: Option<Pattern>,
+
+    #[arg(
+        long = "regex",
+        help = "Regular expression to select repositories by their path"
+    )]
+    pub regex: Option<Regex>,
+
+    #[arg(
+        long = "has-remote",
+        conflicts_with = "no_remote",
+        help = "Select repositories which have a remote"
+    )]
+    pub has_remote: bool,
+
+    #[arg(long = "no-remote", help = "Select repositories which have no remote")]
+    pub no_remote: bool,
+}
+
+impl Filter {
+    pub fn matches(&self, repo: &Repository) -> bool {
+        let path = repo.path_string();
+
+        if let Some(ref pattern) = self.pattern {
+            if !path.to_lowercase().contains(&pattern.to_lowercase()) {
+                return false;
+            }
+        }
+
+        if !self.vcs.is_empty() && !self.vcs.contains(&repo.vcs()) {
+            return false;
+        }
+
+        let remote = repo.remote();
+        if !self.host.is_empty() {
+            let host = remote.and_then(|remote| remote.host());
+            if !self
+                .host
+                .iter()
+                .any(|h| host.is_some_and(|host| host.eq_ignore_ascii_case(h)))
+            {
+                return false;
+            }
+        }
+        if !self.owner.is_empty() {
+            let owner = remote.and_then(|remote| remote.owner());
+            if !self
+                .owner
+                .iter()
+                .any(|o| owner.is_some_and(|owner| owner.eq_ignore_ascii_case(o)))
+            {
+                return false;
+            }
+        }
+
+        if let Some(ref glob) = self.glob {
+            if !glob.matches(repo.name()) && !glob.matches(&path) {
+                return false;
+            }
+        }
+
+        if let Some(ref regex) = self.regex {
+            if !regex.is_match(&path) {
+                return false;
+            }
+        }
+
+        if self.has_remote && remote.is_none() {
+            return false;
+        }
+        if self.no_remote && remote.is_some() {
+            return false;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::test_repository as repository;
+
+    #[test]
+    fn empty_filter_matches_all() {
+        let filter = Filter::default();
+        assert!(filter.matches(&repository(None)));
+        assert!(filter.matches(&repository(Some("https://github.com/a/b.git"))));
+    }
+
+    #[test]
+    fn filter_by_host_and_owner() {
+        let repo = repository(Some("git@github.com:Ubnt-Intrepid/rhq.git"));
+
+        let mut filter = Filter {
+            host: vec!["github.com".to_owned()],
+            ..Default::default()
+        };
+        assert!(filter.matches(&repo));
+
+        filter.owner = vec!["ubnt-intrepid".to_owned()];
+        assert!(filter.matches(&repo));
+
+        filter.owner = vec!["someone".to_owned()];
+        assert!(!filter.matches(&repo));
+    }
+
+    #[test]
+    fn filter_by_vcs_and_remote() {
+        let repo = repository(None);
+        let filter = Filter {
+            vcs: vec![Vcs::HG],
+            ..Default::default()
+        };
+        assert!(!filter.matches(&repo));
+
+        let filter = Filter {
+            has_remote: true,
+            ..Default::default()
+        };
+        assert!(!filter.matches(&repo));
+
+        let filter = Filter {
+            no_remote: true,
+            ..Default::default()
+        };
+        assert!(filter.matches(&repo));
+    }
+}
@@ -0,0 +1,213 @@
+//! Templates to determine the location of local repositories.
+
+use anyhow::{anyhow, Result};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+pub const DEFAULT_LAYOUT: &str = "{root}/{host}/{path}";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Placeholder {
+    Host,
+    Path,
+    Owner,
+    Repo,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+/// Directory layout of local repositories, e.g. `{root}/{host}/{path}`.
+///
+/// Available placeholders are:
+///
+/// * `{root}` - the root directory. It can appear only at the beginning of the template.
+/// * `{host}` - the host name of remote repository
+/// * `{path}` - the path of repository, e.g. `owner/repo`
+/// * `{owner}` - the path without the last component
+/// * `{repo}` - the last component of the path
+///
+/// If the rendered path is relative, it is treated as relative to the root directory.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    template: String,
+    rooted: bool,
+    segments: Vec<Segment>,
+}
+
+impl Layout {
+    pub fn render(&self, root: &Path, host: &str, path: &str) -> PathBuf {
+        let path = path.trim_matches('/');
+        let (owner, repo) = match path.rfind('/') {
+            Some(i) => (&path[..i], &path[i + 1..]),
+            None => ("", path),
+        };
+
+        let mut rendered = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(s) => rendered.push_str(s),
+                Segment::Placeholder(Placeholder::Host) => rendered.push_str(host),
+                Segment::Placeholder(Placeholder::Path) => rendered.push_str(path),
+                Segment::Placeholder(Placeholder::Owner) => rendered.push_str(owner),
+                Segment::Placeholder(Placeholder::Repo) => rendered.push_str(repo),
+            }
+        }
+
+        if !self.rooted && Path::new(&rendered).is_absolute() {
+            return PathBuf::from(rendered);
+        }
+        let mut result = root.to_owned();
+        for component in rendered.split('/').filter(|s| !s.is_empty()) {
+            result.push(component);
+        }
+        result
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        DEFAULT_LAYOUT.parse().expect("should be a valid layout")
+    }
+}
+
+impl FromStr for Layout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let template = s.replace('\\', "/");
+        let (rooted, body) = match template.strip_prefix("{root}") {
+            Some(body) => (true, body.to_owned()),
+            None => (false, shellexpand::full(&template)?.into_owned()),
+        };
+
+        let mut segments = Vec::new();
+        let mut rest = body.as_str();
+        while !rest.is_empty() {
+            let start = match rest.find('{') {
+                Some(start) => start,
+                None => {
+                    if rest.contains('}') {
+                        return Err(anyhow!("unmatched '}}' in layout '{}'", template));
+                    }
+                    segments.push(Segment::Literal(rest.to_owned()));
+                    break;
+                }
+            };
+            if start > 0 {
+                if rest[..start].contains('}') {
+                    return Err(anyhow!("unmatched '}}' in layout '{}'", template));
+                }
+                segments.push(Segment::Literal(rest[..start].to_owned()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .map(|end| start + end)
+                .ok_or_else(|| anyhow!("unclosed '{{' in layout '{}'", template))?;
+            let placeholder = match &rest[start + 1..end] {
+                "host" => Placeholder::Host,
+                "path" => Placeholder::Path,
+                "owner" => Placeholder::Owner,
+                "repo" => Placeholder::Repo,
+                "root" => {
+                    return Err(anyhow!(
+                        "{{root}} must be at the beginning of layout '{}'",
+                        template
+                    ))
+                }
+                name => {
+                    return Err(anyhow!(
+                        "unknown placeholder {{{}}} in layout '{}'",
+                        name,
+                        template
+                    ))
+                }
+            };
+            segments.push(Segment::Placeholder(placeholder));
+            rest = &rest[end + 1..];
+        }
+
+        if !segments.iter().any(|s| {
+            matches!(
+                s,
+                Segment::Placeholder(Placeholder::Path | Placeholder::Repo)
+            )
+        }) {
+            return Err(anyhow!(
+                "layout '{}' must contain {{path}} or {{repo}}",
+                template
+            ));
+        }
+
+        Ok(Layout {
+            template,
+            rooted,
+            segments,
+        })
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_layout() {
+        let layout = Layout::default();
+        assert_eq!(
+            layout.render(Path::new("/rhq"), "github.com", "ubnt-intrepid/rhq"),
+            Path::new("/rhq/github.com/ubnt-intrepid/rhq")
+        );
+    }
+
+    #[test]
+    fn owner_and_repo() {
+        let layout: Layout = "{root}/{owner}/{repo}".parse().unwrap();
+        assert_eq!(
+            layout.render(Path::new("/rhq"), "gitlab.com", "group/sub/repo"),
+            Path::new("/rhq/group/sub/repo")
+        );
+        assert_eq!(
+            layout.render(Path::new("/rhq"), "gitlab.com", "repo"),
+            Path::new("/rhq/repo")
+        );
+    }
+
+    #[test]
+    fn relative_to_root() {
+        let layout: Layout = "{host}-{repo}".parse().unwrap();
+        assert_eq!(
+            layout.render(Path::new("/rhq"), "github.com", "ubnt-intrepid/rhq"),
+            Path::new("/rhq/github.com-rhq")
+        );
+    }
+
+    #[test]
+    fn absolute_path() {
+        let layout: Layout = "/src/{path}".parse().unwrap();
+        assert_eq!(
+            layout.render(Path::new("/rhq"), "github.com", "ubnt-intrepid/rhq"),
+            Path::new("/src/ubnt-intrepid/rhq")
+        );
+    }
+
+    #[test]
+    fn invalid_layouts() {
+        assert!("{root}/{host}".parse::<Layout>().is_err());
+        assert!("{root}/{unknown}/{path}".parse::<Layout>().is_err());
+        assert!("{root}/{host/{path}".parse::<Layout>().is_err());
+        assert!("{host}/{root}/{path}".parse::<Layout>().is_err());
+    }
+}
@@ -2,28 +2,35 @@
 //! rhq is an alternative of `ghq`
 //!
 
-#![forbid(clippy::todo, clippy::unimplemented)]
+// `forbid` would reject the `#[allow]` attributes in the code generated by clap derive (E0453).
+#![deny(clippy::todo, clippy::unimplemented)]
 #![cfg_attr(test, deny(warnings))]
 
 pub mod cli;
 
 mod cache;
 mod config;
+mod filter;
+mod layout;
+mod pool;
 mod printer;
 mod query;
 mod remote;
 mod repository;
 mod scp;
+mod sort;
 mod util;
 mod vcs;
 mod workspace;
 
 pub use crate::{
-    cache::Cache, //
+    cache::Cache,
     config::Config,
+    filter::Filter,
     query::Query,
     remote::Remote,
     repository::Repository,
-    vcs::Vcs,
+    sort::SortKey,
+    vcs::{Commit, Status, Vcs, VcsBackend},
     workspace::Workspace,
 };
@@ -1,21 +1,45 @@
 use anyhow::Context as _;
-use clap::Parser as _;
+use clap::{CommandFactory as _, Parser as _};
 use rhq::{cli::Args, Cache, Config, Workspace};
 
 fn main() -> anyhow::Result<()> {
     better_panic::install();
     pretty_env_logger::init();
 
+    // Respond to the dynamic completion requested by the shell, if any.
+    clap_complete::CompleteEnv::with_factory(Args::command).complete();
+
     let args = Args::parse();
     log::debug!("operation={:?}", args);
 
-    let mut config = Config::new(None) //
+    let config = Config::new(None) //
         .context("failed to load the configuration file")?;
-    let mut cache = Cache::new(&config.cache_dir()) //
+    let cache_path = config.cache_dir();
+
+    let mut cache = Cache::new(&cache_path) //
         .context("failed to load the repository cache")?;
+    let backup_path = if cache.needs_rebuild() {
+        cache.backup(&cache_path)?
+    } else {
+        None
+    };
 
-    let mut workspace = Workspace::new(&mut cache, &mut config);
+    let mut workspace = Workspace::new(&mut cache, &config);
     workspace.set_verbose_output(args.verbose);
+    if let Some(jobs) = args.jobs {
+        workspace.set_jobs(jobs);
+    }
+
+    if let Some(backup_path) = backup_path {
+        eprintln!(
+            "warning: the repository cache is broken and has been moved to {}; rebuilding it",
+            backup_path.display()
+        );
+        workspace
+            .rebuild_repositories()
+            .context("failed to rebuild the repository cache")?;
+        workspace.save_cache()?;
+    }
 
     args.run(&mut workspace)?;
 
@@ -0,0 +1,154 @@
+//! Bounded worker pool for operations over many repositories.
+
+use std::{
+    sync::{mpsc, Mutex},
+    thread,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Pool {
+    jobs: usize,
+}
+
+impl Pool {
+    pub fn new(jobs: usize) -> Self {
+        Pool { jobs: jobs.max(1) }
+    }
+
+    pub fn jobs(&self) -> usize {
+        self.jobs
+    }
+
+    /// Apply `f` to each item by using at most `jobs` worker threads.
+    ///
+    /// `on_done` is called on the calling thread with each result in order of completion,
+    /// so it can print per-item output without interleaving.
+    /// If `on_done` returns `false`, the items which have not started yet are skipped,
+    /// while the results of the running ones are still passed to `on_done`.
+    pub fn for_each<T, R, F, G>(&self, items: Vec<T>, f: F, mut on_done: G)
+    where
+        T: Send,
+        R: Send,
+        F: Fn(T) -> R + Sync,
+        G: FnMut(R) -> bool,
+    {
+        if self.jobs == 1 || items.len() <= 1 {
+            for item in items {
+                if !on_done(f(item)) {
+                    break;
+                }
+            }
+            return;
+        }
+
+        let num_workers = self.jobs.min(items.len());
+        let queue = Mutex::new(items.into_iter());
+        let (tx, rx) = mpsc::channel();
+        thread::scope(|scope| {
+            for _ in 0..num_workers {
+                let tx = tx.clone();
+                let (queue, f) = (&queue, &f);
+                scope.spawn(move || loop {
+                    let item = match queue.lock().unwrap().next() {
+                        Some(item) => item,
+                        None => break,
+                    };
+                    if tx.send(f(item)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(tx);
+
+            let mut cancelled = false;
+            for result in rx {
+                if !on_done(result) && !cancelled {
+                    // Discard the pending items, so that the workers stop after the current ones.
+                    queue.lock().unwrap().by_ref().for_each(drop);
+                    cancelled = true;
+                }
+            }
+        });
+    }
+
+    /// Apply `f` to each item in parallel, and return the results in the original order.
+    pub fn map<T, R, F>(&self, items: Vec<T>, f: F) -> Vec<R>
+    where
+        T: Send,
+        R: Send,
+        F: Fn(T) -> R + Sync,
+    {
+        let mut results: Vec<Option<R>> = (0..items.len()).map(|_| None).collect();
+        self.for_each(
+            items.into_iter().enumerate().collect(),
+            |(i, item)| (i, f(item)),
+            |(i, result)| {
+                results[i] = Some(result);
+                true
+            },
+        );
+        results.into_iter().map(Option::unwrap).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_preserves_order() {
+        let pool = Pool::new(4);
+        let results = pool.map((0..100).collect(), |i: u64| i * 2);
+        assert_eq!(results, (0..100).map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn for_each_stops_after_cancel() {
+        let pool = Pool::new(1);
+        let mut seen = Vec::new();
+        pool.for_each(
+            (0..10).collect(),
+            |i: u32| i,
+            |i| {
+                seen.push(i);
+                i < 3
+            },
+        );
+        assert_eq!(seen, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn for_each_reports_running_items_after_cancel() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Barrier,
+        };
+
+        // The first four items run at the same time, and the first result cancels the rest.
+        // Each worker may start at most one more item before the cancel.
+        let pool = Pool::new(4);
+        let barrier = Barrier::new(4);
+        let started = AtomicUsize::new(0);
+        let mut seen = Vec::new();
+        pool.for_each(
+            (0..16).collect(),
+            |i: u32| {
+                started.fetch_add(1, Ordering::SeqCst);
+                if i < 4 {
+                    barrier.wait();
+                } else {
+                    thread::sleep(std::time::Duration::from_millis(100));
+                }
+                i
+            },
+            |i| {
+                seen.push(i);
+                false
+            },
+        );
+        seen.sort();
+        assert!(seen.starts_with(&[0, 1, 2, 3]));
+        assert_eq!(seen.len(), started.load(Ordering::SeqCst));
+        assert!(seen.len() <= 8);
+    }
+}
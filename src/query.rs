@@ -1,6 +1,6 @@
 use crate::scp::ScpPath;
 use anyhow::{anyhow, Result};
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 use url::Url;
 
 /// Represents query from user.
@@ -12,11 +12,18 @@ use url::Url;
 /// * `<username>@<host>:<path-to-repo>`
 ///   - Equivalent to `ssh://<username>@<host>/<path-to-repo>.git`
 /// * `<path-to-repo>`
+/// * `<alias>:<path-to-repo>`
+///   - Parsed as the SCP pattern, and then expanded by `Query::expand_alias`.
 #[derive(Debug, Clone)]
 pub enum Query {
     Url(Url),
     Scp(ScpPath),
     Path(String),
+    /// `<path-to-repo>` on a specific host, expanded from an alias.
+    HostPath {
+        host: String,
+        path: String,
+    },
 }
 
 impl Query {
@@ -26,6 +33,7 @@ impl Query {
             Query::Url(ref url) => url.host_str(),
             Query::Scp(ref scp) => Some(scp.host()),
             Query::Path(_) => None,
+            Query::HostPath { ref host, .. } => Some(host),
         }
     }
 
@@ -34,15 +42,77 @@ impl Query {
             Query::Url(ref url) => url.path().trim_start_matches('/').trim_end_matches(".git"),
             Query::Scp(ref scp) => scp.path(),
             Query::Path(ref path) => path,
+            Query::HostPath { ref path, .. } => path,
         }
     }
+
+    /// Expand the query of the form `<alias>:<path-to-repo>`.
+    ///
+    /// Returns `None` if the query does not start with any of `aliases`.
+    /// SCP patterns with an explicit user name, such as `git@<alias>:<path>`, are never expanded.
+    pub fn expand_alias(&self, aliases: &HashMap<String, Alias>) -> Option<Query> {
+        let scp = match *self {
+            Query::Scp(ref scp) if scp.explicit_username().is_none() => scp,
+            _ => return None,
+        };
+        let alias = aliases.get(scp.host())?;
+        let path = match alias.prefix {
+            Some(ref prefix) => format!("{}/{}", prefix, scp.path()),
+            None => scp.path().to_owned(),
+        };
+        Some(Query::HostPath {
+            host: alias.host.clone(),
+            path,
+        })
+    }
+}
+
+/// User-defined shorthand of a host and an optional owner, e.g. `github.com/ubnt-intrepid`.
+#[derive(Debug, Clone)]
+pub struct Alias {
+    host: String,
+    prefix: Option<String>,
+}
+
+impl Alias {
+    /// Check whether `name` can be used as an alias without ambiguity.
+    pub fn validate_name(name: &str) -> Result<()> {
+        if name.is_empty() || name.contains(['.', '@', ':', '/', '\\']) {
+            return Err(anyhow!(
+                "'{}' is an invalid alias name; it must not contain '.', '@', ':' or '/'",
+                name
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Alias {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Alias> {
+        let s = s.trim_matches('/');
+        let (host, prefix) = match s.find('/') {
+            Some(i) => (&s[..i], Some(s[i + 1..].to_owned())),
+            None => (s, None),
+        };
+        if host.is_empty() {
+            return Err(anyhow!("'{}' is an invalid alias; the host is empty", s));
+        }
+        Ok(Alias {
+            host: host.to_owned(),
+            prefix,
+        })
+    }
 }
 
 impl FromStr for Query {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Query> {
-        if let Ok(url) = Url::parse(s) {
+        // Strings like `<alias>:<path>` are also valid URLs whose scheme is `<alias>`,
+        // so only the URLs with authority (`<scheme>://...`) are treated as URL.
+        if let Some(url) = Url::parse(s).ok().filter(|url| !url.cannot_be_a_base()) {
             match url.scheme() {
                 "http" | "https" | "ssh" | "git" => {}
                 scheme => return Err(anyhow!("'{}' is invalid scheme", scheme)),
@@ -166,3 +236,53 @@ mod test_methods {
         assert_eq!(query.path(), "ubnt-intrepid/dot");
     }
 }
+
+#[cfg(test)]
+mod tests_alias {
+    use super::{Alias, Query};
+    use std::collections::HashMap;
+
+    fn aliases() -> HashMap<String, Alias> {
+        let mut aliases = HashMap::new();
+        aliases.insert("gh".to_owned(), "github.com".parse().unwrap());
+        aliases.insert("work".to_owned(), "git.example.com/team".parse().unwrap());
+        aliases
+    }
+
+    #[test]
+    fn expand_host_alias() {
+        let query: Query = "gh:ubnt-intrepid/rhq".parse().unwrap();
+        let query = query.expand_alias(&aliases()).unwrap();
+        assert_eq!(query.host(), Some("github.com"));
+        assert_eq!(query.path(), "ubnt-intrepid/rhq");
+    }
+
+    #[test]
+    fn expand_owner_alias() {
+        let query: Query = "work:repo".parse().unwrap();
+        let query = query.expand_alias(&aliases()).unwrap();
+        assert_eq!(query.host(), Some("git.example.com"));
+        assert_eq!(query.path(), "team/repo");
+    }
+
+    #[test]
+    fn keep_explicit_scp() {
+        let query: Query = "git@gh:ubnt-intrepid/rhq".parse().unwrap();
+        assert!(query.expand_alias(&aliases()).is_none());
+        assert_eq!(query.host(), Some("gh"));
+    }
+
+    #[test]
+    fn keep_unknown_host() {
+        let query: Query = "github.com:ubnt-intrepid/rhq".parse().unwrap();
+        assert!(query.expand_alias(&aliases()).is_none());
+    }
+
+    #[test]
+    fn invalid_alias_names() {
+        assert!(Alias::validate_name("gh").is_ok());
+        assert!(Alias::validate_name("github.com").is_err());
+        assert!(Alias::validate_name("git@gh").is_err());
+        assert!(Alias::validate_name("").is_err());
+    }
+}
@@ -1,59 +1,300 @@
 use crate::{query::Query, scp::ScpPath};
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
+use std::{
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
 use url::Url;
 
+/// Protocol used to access remote repositories.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Https,
+    Ssh,
+}
+
 /// Information of remote repository
+///
+/// Two remotes are equal if they have the same canonical key,
+/// i.e. the different spellings of the same repository are identical.
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "RawRemote", into = "RawRemote")]
 pub struct Remote {
+    /// the URL passed to VCS commands
     url: String,
+    /// URL scheme (`"ssh"` for SCP-like syntax, and `"file"` for local paths)
+    scheme: String,
+    username: Option<String>,
+    host: Option<String>,
+    port: Option<u16>,
+    /// path of the repository, without the leading slash of URLs
+    path: String,
+}
+
+// serialized representation of `Remote`, kept compatible with older cache files.
+#[derive(Clone, Serialize, Deserialize)]
+struct RawRemote {
+    url: String,
+}
+
+// Loading never fails, since the cache may contain the remotes recorded before validation was introduced.
+impl From<RawRemote> for Remote {
+    fn from(raw: RawRemote) -> Self {
+        Remote::parse(raw.url)
+    }
+}
+
+impl From<Remote> for RawRemote {
+    fn from(remote: Remote) -> Self {
+        RawRemote { url: remote.url }
+    }
 }
 
 impl Remote {
-    pub fn new<S: Into<String>>(url: S) -> Remote {
-        // TODO: verify URL
-        Remote { url: url.into() }
+    pub fn new<S: Into<String>>(url: S) -> Result<Remote> {
+        let remote = Self::parse(url.into());
+        remote.validate()?;
+        Ok(remote)
+    }
+
+    fn parse(url: String) -> Remote {
+        let parsed = Url::parse(&url).ok();
+
+        // `file://` URLs are local paths, which have no host.
+        if let Some(parsed) = parsed.as_ref().filter(|parsed| parsed.scheme() == "file") {
+            let path = parsed.to_file_path().map_or_else(
+                |()| parsed.path().to_owned(),
+                |path| path.display().to_string(),
+            );
+            return Remote {
+                scheme: "file".to_owned(),
+                username: None,
+                host: None,
+                port: None,
+                path,
+                url,
+            };
+        }
+
+        if let Some(parsed) =
+            parsed.filter(|parsed| parsed.has_host() && !parsed.cannot_be_a_base())
+        {
+            return Remote {
+                scheme: parsed.scheme().to_owned(),
+                username: Some(parsed.username())
+                    .filter(|s| !s.is_empty())
+                    .map(ToOwned::to_owned),
+                host: parsed.host_str().map(ToOwned::to_owned),
+                port: parsed.port(),
+                path: parsed.path().trim_start_matches('/').to_owned(),
+                url,
+            };
+        }
+
+        if let Some((username, host, path)) = split_scp_like(&url) {
+            return Remote {
+                scheme: "ssh".to_owned(),
+                username: username.map(ToOwned::to_owned),
+                host: Some(host.to_owned()),
+                port: None,
+                path: path.to_owned(),
+                url,
+            };
+        }
+
+        Remote {
+            scheme: "file".to_owned(),
+            username: None,
+            host: None,
+            port: None,
+            path: url.clone(),
+            url,
+        }
     }
 
     pub fn url(&self) -> &str {
         &self.url
     }
+
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    pub fn username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+
+    /// Returns the host name, or `None` if the remote is a local path.
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the path of the repository on the host without the `.git` suffix,
+    /// e.g. `ubnt-intrepid/rhq` for `ubnt-intrepid/rhq.git`.
+    ///
+    /// Returns `None` if the remote is a local path.
+    pub fn repo_path(&self) -> Option<&str> {
+        self.host.as_ref()?;
+        let path = self.path.trim_matches('/');
+        Some(path.strip_suffix(".git").unwrap_or(path))
+    }
+
+    /// Returns the owner part of the path, e.g. `ubnt-intrepid` for `ubnt-intrepid/rhq.git`.
+    ///
+    /// Nested groups are contained as is, e.g. `group/sub` for `group/sub/repo`.
+    pub fn owner(&self) -> Option<&str> {
+        self.host.as_ref()?;
+        let path = self.path.trim_matches('/');
+        path.rfind('/').map(|i| &path[..i])
+    }
+
+    /// Returns the key to identify the remote repository regardless of its spelling.
+    ///
+    /// The key consists of the host name, the port number (unless it is the default one
+    /// of the scheme) and the normalized path, which are case-folded and do not contain
+    /// the scheme, user name and `.git` suffix.
+    pub fn canonical_key(&self) -> String {
+        let path = self
+            .path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("/");
+        let path = path.strip_suffix(".git").unwrap_or(&path);
+        let port = self
+            .port
+            .filter(|&port| Some(port) != default_port(&self.scheme));
+        match (&self.host, port) {
+            (Some(host), Some(port)) => format!("{}:{}/{}", host, port, path).to_lowercase(),
+            (Some(host), None) => format!("{}/{}", host, path).to_lowercase(),
+            (None, _) => path.to_lowercase(),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.url.is_empty() {
+            return Err(anyhow!("empty remote URL"));
+        }
+        // Whitespaces are valid in local paths and URLs, e.g. `/home/me/My Repos/foo.git`.
+        if self.url.chars().any(char::is_control) {
+            return Err(anyhow!("'{}' contains invalid characters", self.url));
+        }
+        if self.host.as_deref() == Some("") {
+            return Err(anyhow!("'{}' has an empty host", self.url));
+        }
+        if self.path.trim_matches('/').is_empty() {
+            return Err(anyhow!("'{}' has an empty path", self.url));
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Remote {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Remote::new(s)
+    }
+}
+
+impl PartialEq for Remote {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical_key() == other.canonical_key()
+    }
+}
+
+impl Eq for Remote {}
+
+impl Hash for Remote {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical_key().hash(state)
+    }
+}
+
+/// Returns the port number used when the URL does not specify it.
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "ssh" | "git+ssh" | "ssh+git" => Some(22),
+        "git" => Some(9418),
+        "http" => Some(80),
+        "https" => Some(443),
+        _ => None,
+    }
+}
+
+/// Split the SCP-like syntax `[<username>@]<host>:<path>` into its components.
+fn split_scp_like(s: &str) -> Option<(Option<&str>, &str, &str)> {
+    let colon = s.find(':')?;
+    let (authority, path) = (&s[..colon], &s[colon + 1..]);
+    // A slash before the colon means a local path, e.g. `./foo:bar`.
+    if authority.contains('/') {
+        return None;
+    }
+    let (username, host) = match authority.rfind('@') {
+        Some(i) => (Some(&authority[..i]), &authority[i + 1..]),
+        None => (None, authority),
+    };
+    // A single letter is a drive letter on Windows, e.g. `C:\foo`.
+    if cfg!(windows) && host.len() == 1 {
+        return None;
+    }
+    Some((username, host, path))
 }
 
 impl Remote {
     pub fn from_url(url: &Url) -> Result<Self> {
-        let url = if url.scheme() == "ssh" {
-            let username = url.username();
-            let host = url.host_str().ok_or_else(|| anyhow!("empty host"))?;
+        let host = url.host_str().ok_or_else(|| anyhow!("empty host"))?;
+        // The SCP-like syntax is used only if it can express the same location,
+        // since it cannot have a port number or a password.
+        if url.scheme() == "ssh" && url.port().is_none() && url.password().is_none() {
             let path = url.path().trim_start_matches('/');
-            format!("{}@{}:{}", username, host, path)
-        } else {
-            url.as_str().to_owned()
-        };
-        Ok(Self { url })
+            let url = match url.username() {
+                "" => format!("{}:{}", host, path),
+                username => format!("{}@{}:{}", username, host, path),
+            };
+            return Self::new(url);
+        }
+        Self::new(url.as_str())
     }
 
-    pub fn from_scp(scp: &ScpPath) -> Self {
-        Self {
-            url: scp.to_string(),
-        }
+    pub fn from_scp(scp: &ScpPath) -> Result<Self> {
+        Self::new(scp.to_string())
     }
 
-    pub fn from_path(path: &str, is_ssh: bool, host: &str) -> Result<Self> {
+    pub fn from_path(path: &str, is_ssh: bool, host: &str, username: Option<&str>) -> Result<Self> {
         if is_ssh {
-            let scp: ScpPath = format!("git@{}:{}", host, path).parse()?;
-            Ok(Self::from_scp(&scp))
+            let username = username.unwrap_or("git");
+            let scp: ScpPath = format!("{}@{}:{}", username, host, path).parse()?;
+            Self::from_scp(&scp)
         } else {
             let url = Url::parse(&format!("https://{}/{}.git", host, path))?;
             Self::from_url(&url)
         }
     }
 
-    pub fn from_query(query: &Query, is_ssh: bool, host: &str) -> Result<Self> {
+    pub fn from_query(
+        query: &Query,
+        is_ssh: bool,
+        host: &str,
+        username: Option<&str>,
+    ) -> Result<Self> {
         match *query {
             Query::Url(ref url) => Self::from_url(url),
-            Query::Scp(ref path) => Ok(Self::from_scp(path)),
-            Query::Path(ref path) => Self::from_path(path, is_ssh, host),
+            Query::Scp(ref path) => Self::from_scp(path),
+            Query::Path(ref path) => Self::from_path(path, is_ssh, host, username),
+            Query::HostPath {
+                ref host, ref path, ..
+            } => Self::from_path(path, is_ssh, host, username),
         }
     }
 }
@@ -66,7 +307,7 @@ mod tests {
     fn path_https() {
         let s = "ubnt-intrepid/rhq";
         let query: Query = s.parse().unwrap();
-        let remote = Remote::from_query(&query, false, "github.com").unwrap();
+        let remote = Remote::from_query(&query, false, "github.com", None).unwrap();
         assert_eq!(remote.url, "https://github.com/ubnt-intrepid/rhq.git");
     }
 
@@ -74,7 +315,156 @@ mod tests {
     fn path_scp() {
         let s = "ubnt-intrepid/rhq";
         let query: Query = s.parse().unwrap();
-        let remote = Remote::from_query(&query, true, "github.com").unwrap();
+        let remote = Remote::from_query(&query, true, "github.com", None).unwrap();
         assert_eq!(remote.url, "git@github.com:ubnt-intrepid/rhq.git");
     }
+
+    #[test]
+    fn ssh_url_with_port() {
+        let s = "ssh://gituser@github.com:2222/peco/peco.git";
+        let query: Query = s.parse().unwrap();
+        let remote = Remote::from_query(&query, false, "github.com", None).unwrap();
+        assert_eq!(remote.url(), "ssh://gituser@github.com:2222/peco/peco.git");
+        assert_eq!(remote.scheme(), "ssh");
+        assert_eq!(remote.username(), Some("gituser"));
+        assert_eq!(remote.host(), Some("github.com"));
+        assert_eq!(remote.port(), Some(2222));
+        assert_eq!(remote.path(), "peco/peco.git");
+    }
+
+    #[test]
+    fn ssh_url_without_port() {
+        let s = "ssh://gituser@github.com/peco/peco.git";
+        let query: Query = s.parse().unwrap();
+        let remote = Remote::from_query(&query, false, "github.com", None).unwrap();
+        assert_eq!(remote.url(), "gituser@github.com:peco/peco.git");
+        assert_eq!(remote.username(), Some("gituser"));
+        assert_eq!(remote.host(), Some("github.com"));
+        assert_eq!(remote.port(), None);
+        assert_eq!(remote.path(), "peco/peco.git");
+    }
+
+    #[test]
+    fn ssh_url_without_username() {
+        let s = "ssh://github.com/peco/peco.git";
+        let query: Query = s.parse().unwrap();
+        let remote = Remote::from_query(&query, false, "github.com", None).unwrap();
+        assert_eq!(remote.url(), "github.com:peco/peco.git");
+        assert_eq!(remote.username(), None);
+    }
+
+    #[test]
+    fn parse_local_path() {
+        let remote = Remote::new("/srv/git/repo.git").unwrap();
+        assert_eq!(remote.scheme(), "file");
+        assert_eq!(remote.host(), None);
+        assert_eq!(remote.path(), "/srv/git/repo.git");
+
+        let file_url = Remote::new("file:///srv/git/repo.git").unwrap();
+        assert_eq!(file_url.scheme(), "file");
+        assert_eq!(file_url.host(), None);
+        assert_eq!(file_url.owner(), None);
+        assert_eq!(file_url.path(), "/srv/git/repo.git");
+        assert_eq!(file_url.url(), "file:///srv/git/repo.git");
+        assert_eq!(file_url.canonical_key(), remote.canonical_key());
+        assert_eq!(file_url, remote);
+    }
+
+    #[test]
+    fn serialize_as_url() {
+        let remote = Remote::new("ssh://git@example.com:2222/foo/bar.git").unwrap();
+        let json = serde_json::to_string(&remote).unwrap();
+        assert_eq!(json, r#"{"url":"ssh://git@example.com:2222/foo/bar.git"}"#);
+        let remote: Remote = serde_json::from_str(&json).unwrap();
+        assert_eq!(remote.port(), Some(2222));
+    }
+
+    #[test]
+    fn path_scp_with_username() {
+        let s = "group/repo";
+        let query: Query = s.parse().unwrap();
+        let remote =
+            Remote::from_query(&query, true, "gitlab.example.com", Some("gitlab")).unwrap();
+        assert_eq!(remote.url, "gitlab@gitlab.example.com:group/repo.git");
+    }
+
+    #[test]
+    fn same_remote_in_different_spellings() {
+        let remotes = [
+            "https://github.com/ubnt-intrepid/rhq.git",
+            "https://github.com/Ubnt-Intrepid/rhq",
+            "git@github.com:ubnt-intrepid/rhq.git",
+            "ssh://git@github.com/ubnt-intrepid/rhq",
+            "ssh://git@github.com:22/ubnt-intrepid/rhq.git/",
+        ];
+        for s in &remotes {
+            let remote = Remote::new(*s).unwrap();
+            assert_eq!(remote.canonical_key(), "github.com/ubnt-intrepid/rhq");
+            assert_eq!(remote, Remote::new(remotes[0]).unwrap());
+        }
+        assert_ne!(
+            Remote::new("https://github.com/ubnt-intrepid/rhq.git").unwrap(),
+            Remote::new("https://gitlab.com/ubnt-intrepid/rhq.git").unwrap()
+        );
+    }
+
+    #[test]
+    fn owner_of_remote() {
+        let remote = Remote::new("https://gitlab.com/group/sub/repo.git").unwrap();
+        assert_eq!(remote.owner(), Some("group/sub"));
+        let remote = Remote::new("git@github.com:ubnt-intrepid/rhq.git").unwrap();
+        assert_eq!(remote.owner(), Some("ubnt-intrepid"));
+        let remote = Remote::new("/srv/git/repo.git").unwrap();
+        assert_eq!(remote.owner(), None);
+    }
+
+    #[test]
+    fn repo_path_of_remote() {
+        let remote = Remote::new("https://gitlab.com/group/sub/repo.git").unwrap();
+        assert_eq!(remote.repo_path(), Some("group/sub/repo"));
+        let remote = Remote::new("ssh://git@example.com:2222/owner/repo").unwrap();
+        assert_eq!(remote.repo_path(), Some("owner/repo"));
+        let remote = Remote::new("/srv/git/repo.git").unwrap();
+        assert_eq!(remote.repo_path(), None);
+    }
+
+    #[test]
+    fn invalid_remotes() {
+        assert!(Remote::new("").is_err());
+        assert!(Remote::new("https://github.com/").is_err());
+        assert!(Remote::new("git@github.com:").is_err());
+        assert!(Remote::new("git@:foo/bar").is_err());
+        assert!(Remote::new("https://github.com/foo\nbar").is_err());
+        assert!(Remote::new("file://").is_err());
+        assert!(Remote::new("file:///").is_err());
+    }
+
+    #[test]
+    fn remotes_with_whitespaces() {
+        let remote = Remote::new("/home/me/My Repos/foo.git").unwrap();
+        assert_eq!(remote.path(), "/home/me/My Repos/foo.git");
+        assert!(Remote::new("https://example.com/my repos/foo.git").is_ok());
+    }
+
+    #[test]
+    fn load_unvalidated_remote() {
+        let remote: Remote = serde_json::from_str(r#"{ "url": "git@github.com:" }"#).unwrap();
+        assert_eq!(remote.url(), "git@github.com:");
+    }
+
+    #[test]
+    fn canonical_key_with_port() {
+        assert_ne!(
+            Remote::new("ssh://git@example.com:2222/a/b.git").unwrap(),
+            Remote::new("https://example.com/a/b.git").unwrap()
+        );
+        assert_eq!(
+            Remote::new("ssh://git@example.com:22/a/b.git").unwrap(),
+            Remote::new("https://example.com/a/b.git").unwrap()
+        );
+        assert_eq!(
+            Remote::new("ssh://git@example.com:2222/a/b.git").unwrap(),
+            Remote::new("https://example.com:2222/a/b").unwrap()
+        );
+    }
 }
@@ -6,11 +6,13 @@ use crate::{
     vcs::Vcs,
 };
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 use std::{
     ffi::OsStr,
     fmt::Display,
     path::{Path, PathBuf},
+    process::Output,
 };
 
 /// local repository
@@ -25,6 +27,24 @@ pub struct Repository {
     /// information of remote repository
     #[serde(skip_serializing_if = "Option::is_none")]
     remote: Option<Remote>,
+    /// when the repository was registered (unknown for entries in older caches)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    added_at: Option<DateTime<Local>>,
+    /// when the repository was entered by `rhq cd` last time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    accessed_at: Option<DateTime<Local>>,
+    /// identifier of the checked out commit, as of the last add, import or refresh
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    head: Option<String>,
+    /// commit time of the checked out revision, as of the last add, import or refresh
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_commit_at: Option<DateTime<Local>>,
+    /// default branch of the remote repository, as of the last add, import or refresh
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_branch: Option<String>,
+    /// short description of the repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
 }
 
 impl Repository {
@@ -39,26 +59,72 @@ impl Repository {
             .file_name()
             .map(|s| s.to_string_lossy().into_owned())
             .ok_or_else(|| anyhow!("cannot determine repository name"))?;
+        // The metadata is informative, so failures to get them are not fatal.
+        let description = vcs.description(&path).ok().flatten();
         Ok(Repository {
             name,
-            path,
             vcs,
             remote: remote.into(),
+            added_at: Some(Local::now()),
+            accessed_at: None,
+            head: None,
+            last_commit_at: None,
+            default_branch: None,
+            description,
+            path,
         })
     }
 
     /// Check existence of repository and drop if not exists.
     pub fn refresh(self) -> Option<Self> {
+        // The repository of an unknown VCS cannot be inspected, and is kept while it exists.
+        if !self.vcs.is_known() {
+            return Some(self).filter(|repo| repo.path.exists());
+        }
         match self.vcs.get_remote_url(&self.path) {
-            Ok(url) => Self::new(self.path, self.vcs, url.map(Remote::new)).ok(),
+            Ok(url) => {
+                let remote = url.and_then(|url| Remote::new(url).ok());
+                let mut repo = Self::new(&self.path, self.vcs, remote).ok()?;
+                repo.inherit_timestamps(&self);
+                repo.load_metadata();
+                Some(repo)
+            }
             _ => None,
         }
     }
 
+    /// Inspect the checked out commit and the default branch.
+    ///
+    /// It requires running the VCS command, so it is done when the repository is
+    /// added, imported or refreshed rather than on every construction.
+    pub fn load_metadata(&mut self) {
+        // The metadata is informative, so failures to get them are not fatal.
+        let head = self.vcs.head_commit(&self.path).ok().flatten();
+        self.last_commit_at = head.as_ref().map(|head| head.time.with_timezone(&Local));
+        self.head = head.map(|head| head.id);
+        self.default_branch = self.vcs.default_branch(&self.path).ok().flatten();
+    }
+
+    /// Take over the registration and access times from the older entry of the same repository.
+    pub fn inherit_timestamps(&mut self, old: &Self) {
+        self.added_at = old.added_at;
+        self.accessed_at = old.accessed_at;
+    }
+
+    /// Record that the repository is accessed now.
+    pub fn touch(&mut self) {
+        self.accessed_at = Some(Local::now());
+    }
+
     pub fn is_same_local(&self, other: &Self) -> bool {
         self.path.as_path() == other.path.as_path()
     }
 
+    /// Check whether the repository is a clone of `remote`.
+    pub fn has_remote(&self, remote: &Remote) -> bool {
+        self.remote.as_ref() == Some(remote)
+    }
+
     pub fn is_contained<P: AsRef<Path>>(&self, path: P) -> bool {
         self.path.starts_with(path)
     }
@@ -76,15 +142,67 @@ impl Repository {
         Ok(output.status.success())
     }
 
+    /// Run command into the repository, and collect its output.
+    pub fn run_command_captured<I, S>(&self, command: &str, args: I) -> Result<Output>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr> + Display,
+    {
+        let output = process::piped(command)
+            .args(args)
+            .current_dir(&self.path)
+            .output()?;
+        Ok(output)
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     pub fn path_string(&self) -> String {
         format!("{}", self.path.display())
     }
 
+    pub fn vcs(&self) -> Vcs {
+        self.vcs
+    }
+
     pub fn remote(&self) -> Option<&Remote> {
         self.remote.as_ref()
     }
+
+    pub fn added_at(&self) -> Option<DateTime<Local>> {
+        self.added_at
+    }
+
+    pub fn accessed_at(&self) -> Option<DateTime<Local>> {
+        self.accessed_at
+    }
+
+    pub fn head(&self) -> Option<&str> {
+        self.head.as_deref()
+    }
+
+    pub fn last_commit_at(&self) -> Option<DateTime<Local>> {
+        self.last_commit_at
+    }
+
+    pub fn default_branch(&self) -> Option<&str> {
+        self.default_branch.as_deref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+}
+
+/// Repository in the temporary directory, cloned from `remote` if given.
+#[cfg(test)]
+pub(crate) fn test_repository(remote: Option<&str>) -> Repository {
+    let remote = remote.map(|url| Remote::new(url).unwrap());
+    Repository::new(std::env::temp_dir(), Vcs::GIT, remote).unwrap()
 }
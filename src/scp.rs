@@ -4,14 +4,19 @@ use std::{fmt, str::FromStr, sync::LazyLock};
 
 #[derive(Debug, Clone)]
 pub struct ScpPath {
-    username: String,
+    username: Option<String>,
     host: String,
     path: String,
 }
 
 impl ScpPath {
     pub fn username(&self) -> &str {
-        &self.username
+        self.username.as_deref().unwrap_or("git")
+    }
+
+    /// Returns the user name only if it is written explicitly.
+    pub fn explicit_username(&self) -> Option<&str> {
+        self.username.as_deref()
     }
 
     pub fn host(&self) -> &str {
@@ -27,7 +32,7 @@ impl FromStr for ScpPath {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<ScpPath> {
-        const RE_SCP: LazyLock<Regex> = LazyLock::new(|| {
+        static RE_SCP: LazyLock<Regex> = LazyLock::new(|| {
             Regex::new(r"^((?:[^@]+@)?)([^:]+):/?(.+)$").expect("should be a valid regex pattern")
         });
         let cap = RE_SCP
@@ -43,9 +48,7 @@ impl FromStr for ScpPath {
                     None
                 }
             })
-            .map(|s| s.trim_end_matches('@'))
-            .unwrap_or("git")
-            .to_owned();
+            .map(|s| s.trim_end_matches('@').to_owned());
         let host = cap.get(2).unwrap().as_str().to_owned();
         let path = cap
             .get(3)
@@ -63,6 +66,6 @@ impl FromStr for ScpPath {
 
 impl fmt::Display for ScpPath {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}@{}:{}.git", self.username, self.host, self.path)
+        write!(f, "{}@{}:{}.git", self.username(), self.host, self.path)
     }
 }
@@ -0,0 +1,84 @@
+//! Ordering of managed repositories.
+
+use crate::repository::Repository;
+use std::{borrow::Borrow, cmp::Ordering};
+
+/// Key to sort repositories.
+///
+/// The time-based keys put the most recent one first, and the repositories
+/// whose time is unknown last. Ties are broken by the path.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum SortKey {
+    Name,
+    Path,
+    Host,
+    Vcs,
+    Added,
+    LastCommit,
+    Recent,
+}
+
+impl SortKey {
+    pub fn compare(self, a: &Repository, b: &Repository) -> Ordering {
+        let ordering = match self {
+            SortKey::Name => a.name().cmp(b.name()),
+            SortKey::Path => Ordering::Equal,
+            SortKey::Host => match (host(a), host(b)) {
+                (Some(a), Some(b)) => a.cmp(b),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            },
+            SortKey::Vcs => a.vcs().to_string().cmp(&b.vcs().to_string()),
+            SortKey::Added => b.added_at().cmp(&a.added_at()),
+            SortKey::LastCommit => b.last_commit_at().cmp(&a.last_commit_at()),
+            SortKey::Recent => b.accessed_at().cmp(&a.accessed_at()),
+        };
+        ordering.then_with(|| a.path().cmp(b.path()))
+    }
+
+    pub fn sort<R: Borrow<Repository>>(self, repos: &mut [R], reverse: bool) {
+        repos.sort_by(|a, b| {
+            let ordering = self.compare(a.borrow(), b.borrow());
+            if reverse {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+}
+
+fn host(repo: &Repository) -> Option<&str> {
+    repo.remote().and_then(|remote| remote.host())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::test_repository as repository;
+
+    #[test]
+    fn sort_by_host_puts_local_remotes_last() {
+        let mut repos = vec![
+            repository(None),
+            repository(Some("https://gitlab.com/a/b.git")),
+            repository(Some("https://github.com/a/b.git")),
+        ];
+        SortKey::Host.sort(&mut repos, false);
+        let hosts: Vec<_> = repos.iter().map(host).collect();
+        assert_eq!(hosts, [Some("github.com"), Some("gitlab.com"), None]);
+
+        SortKey::Host.sort(&mut repos, true);
+        let hosts: Vec<_> = repos.iter().map(host).collect();
+        assert_eq!(hosts, [None, Some("gitlab.com"), Some("github.com")]);
+    }
+
+    #[test]
+    fn sort_by_recent_access() {
+        let mut touched = repository(Some("https://github.com/a/touched.git"));
+        touched.touch();
+        let mut repos = vec![repository(None), touched];
+        SortKey::Recent.sort(&mut repos, false);
+        assert!(repos[0].accessed_at().is_some());
+        assert!(repos[1].accessed_at().is_none());
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::Result;
-use shellexpand;
 use std::borrow::Borrow;
+use std::ffi::OsString;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -28,41 +28,41 @@ pub fn canonicalize_pretty<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
     path.as_ref().canonicalize().map_err(Into::into)
 }
 
-pub trait StrSkip {
-    fn skip(&self, n: usize) -> &str;
-}
-
-impl StrSkip for str {
-    fn skip(&self, n: usize) -> &str {
-        let mut s = self.chars();
-        for _ in 0..n {
-            s.next();
-        }
-        s.as_str()
-    }
-}
-
-#[test]
-fn test_skipped_1() {
-    assert_eq!("hoge".skip(1), "oge");
-    assert_eq!("あいueo".skip(1), "いueo");
-}
-
+/// Write the content to the file at `path` atomically.
+///
+/// The content is written into a temporary file in the same directory first,
+/// and then it is renamed to `path`, so that the readers never see a partially written file.
 pub fn write_content<P, F>(path: P, write_fn: F) -> Result<()>
 where
     P: AsRef<Path>,
     F: FnOnce(&mut fs::File) -> Result<()>,
 {
-    fs::create_dir_all(path.as_ref().parent().unwrap())?;
-    let mut file = fs::OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(&path)?;
-    write_fn(&mut file)
+    let path = path.as_ref();
+    let dir = path.parent().unwrap();
+    fs::create_dir_all(dir)?;
+
+    let mut tmp_name = OsString::from(".");
+    tmp_name.push(path.file_name().unwrap());
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp_path = dir.join(tmp_name);
+
+    let result = fs::File::create(&tmp_path)
+        .map_err(Into::into)
+        .and_then(|mut file| {
+            write_fn(&mut file)?;
+            file.sync_all()?;
+            Ok(())
+        })
+        .and_then(|()| fs::rename(&tmp_path, path).map_err(Into::into));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
 }
 
 pub mod process {
+    use anyhow::{anyhow, Result};
+    use std::path::Path;
     use std::process::{Command, Stdio};
 
     pub fn inherit(name: &str) -> Command {
@@ -80,4 +80,19 @@ pub mod process {
         command.stderr(Stdio::piped());
         command
     }
+
+    /// Run the command `name` in `path` quietly, with its stderr as the error message on failure.
+    pub fn run<P: AsRef<Path>>(name: &str, path: P, args: &[&str]) -> Result<()> {
+        let output = piped(name).args(args).current_dir(path).output()?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "{} {}: {}",
+                name,
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
 }
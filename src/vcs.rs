@@ -1,34 +1,189 @@
 pub mod darcs;
 pub mod git;
 pub mod hg;
+pub mod jj;
 pub mod pijul;
 
-use crate::util::StrSkip;
 use anyhow::{anyhow, Result};
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
-    ffi::OsStr,
+    ffi::{OsStr, OsString},
     fmt::{self, Display},
     path::Path,
     str::FromStr,
+    sync::{LazyLock, RwLock},
 };
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub enum Vcs {
-    Git,
-    Hg,
-    Darcs,
-    Pijul,
+/// Operations of a version control system.
+///
+/// Only the detection, initialization and cloning are mandatory.
+/// The other operations fail with an error unless they are implemented.
+pub trait VcsBackend: Send + Sync {
+    /// Name of the VCS used in the command line and the cache file, e.g. `"git"`.
+    fn name(&self) -> &'static str;
+
+    /// Returns whether the directory at `path` is a working tree managed by this VCS.
+    fn detect(&self, path: &Path) -> bool;
+
+    fn init(&self, path: &Path) -> Result<()>;
+
+    fn clone(&self, url: &str, path: &Path, args: &[OsString]) -> Result<()>;
+
+    /// Returns the URL of the default remote, or `None` if it is not set.
+    fn get_remote_url(&self, _path: &Path) -> Result<Option<String>> {
+        Err(unsupported(self.name()))
+    }
+
+    /// Set the URL of the default remote.
+    fn set_remote_url(&self, _path: &Path, _url: &str) -> Result<()> {
+        Err(unsupported(self.name()))
+    }
+
+    /// Download the changes from the default remote without touching the working tree.
+    fn fetch(&self, _path: &Path) -> Result<()> {
+        Err(unsupported(self.name()))
+    }
+
+    /// Bring the current branch up to date with the fetched changes, without creating any merge.
+    fn pull(&self, _path: &Path) -> Result<()> {
+        Err(unsupported(self.name()))
+    }
+
+    /// Returns the current state of the working tree.
+    fn status(&self, _path: &Path) -> Result<Status> {
+        Err(unsupported(self.name()))
+    }
+
+    /// Returns the name of the current branch.
+    fn current_branch(&self, path: &Path) -> Result<Option<String>> {
+        self.status(path).map(|status| status.branch)
+    }
+
+    /// Returns the currently checked out commit, or `None` if there are no commits yet.
+    fn head_commit(&self, _path: &Path) -> Result<Option<Commit>> {
+        Err(unsupported(self.name()))
+    }
+
+    /// Returns the name of the default branch of the remote.
+    fn default_branch(&self, _path: &Path) -> Result<Option<String>> {
+        Err(unsupported(self.name()))
+    }
+
+    /// Returns the description of the repository set by the user, if any.
+    fn description(&self, _path: &Path) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+fn unsupported(name: &str) -> anyhow::Error {
+    Unsupported(name.to_owned()).into()
 }
 
+/// Error of an operation which is not implemented by the backend.
+#[derive(Debug)]
+pub struct Unsupported(String);
+
+impl Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "This operation is not supported by {} yet", self.0)
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+/// Registered backends, in the order of precedence for detection.
+static BACKENDS: LazyLock<RwLock<Vec<Vcs>>> =
+    LazyLock::new(|| RwLock::new(vec![Vcs::JJ, Vcs::GIT, Vcs::HG, Vcs::DARCS, Vcs::PIJUL]));
+
+/// Placeholders of the unknown VCSs found in the cache, which are never detected.
+static UNKNOWN: LazyLock<RwLock<Vec<Vcs>>> = LazyLock::new(Default::default);
+
+/// Backend of a VCS which is not registered, e.g. the cache was written by a build
+/// of rhq with an additional backend. Every operation fails.
+struct Unknown {
+    name: &'static str,
+}
+
+impl VcsBackend for Unknown {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn detect(&self, _path: &Path) -> bool {
+        false
+    }
+
+    fn init(&self, _path: &Path) -> Result<()> {
+        Err(unsupported(self.name))
+    }
+
+    fn clone(&self, _url: &str, _path: &Path, _args: &[OsString]) -> Result<()> {
+        Err(unsupported(self.name))
+    }
+}
+
+/// Handle of a VCS backend.
+///
+/// The backends are identified by their names.
+#[derive(Clone, Copy)]
+pub struct Vcs(&'static dyn VcsBackend);
+
 impl Vcs {
-    pub fn do_init<P: AsRef<Path>>(self, path: P) -> Result<()> {
-        match self {
-            Vcs::Git => git::init(path),
-            Vcs::Hg => hg::init(path),
-            Vcs::Darcs => darcs::initialize(path),
-            Vcs::Pijul => pijul::init(path),
+    pub const GIT: Vcs = Vcs(&git::Git);
+    pub const HG: Vcs = Vcs(&hg::Hg);
+    pub const DARCS: Vcs = Vcs(&darcs::Darcs);
+    pub const PIJUL: Vcs = Vcs(&pijul::Pijul);
+    pub const JJ: Vcs = Vcs(&jj::Jj);
+
+    /// Register a custom backend, and returns its handle.
+    ///
+    /// The registered backends take precedence over the existing ones in the detection.
+    pub fn register<B: VcsBackend + 'static>(backend: B) -> Result<Vcs> {
+        let mut backends = BACKENDS.write().unwrap_or_else(|e| e.into_inner());
+        if backends.iter().any(|vcs| vcs.name() == backend.name()) {
+            return Err(anyhow!("VCS '{}' is already registered", backend.name()));
         }
+        let vcs = Vcs(Box::leak(Box::new(backend)));
+        backends.insert(0, vcs);
+        Ok(vcs)
+    }
+
+    /// Returns all registered backends.
+    pub fn backends() -> Vec<Vcs> {
+        BACKENDS.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Returns the registered backend of `name`, or a placeholder which fails every operation.
+    fn from_name(name: &str) -> Vcs {
+        if let Ok(vcs) = name.parse() {
+            return vcs;
+        }
+        let mut unknown = UNKNOWN.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(vcs) = unknown.iter().find(|vcs| vcs.name() == name) {
+            return *vcs;
+        }
+        let name = Box::leak(name.to_owned().into_boxed_str());
+        let vcs = Vcs(Box::leak(Box::new(Unknown { name })));
+        unknown.push(vcs);
+        vcs
+    }
+
+    /// Returns whether the backend is registered.
+    pub fn is_known(self) -> bool {
+        Vcs::backends().contains(&self)
+    }
+
+    pub fn name(self) -> &'static str {
+        self.0.name()
+    }
+
+    pub fn backend(self) -> &'static dyn VcsBackend {
+        self.0
+    }
+
+    pub fn do_init<P: AsRef<Path>>(self, path: P) -> Result<()> {
+        self.0.init(path.as_ref())
     }
 
     pub fn do_clone<P, U, I, S>(self, path: P, url: U, args: I) -> Result<()>
@@ -38,57 +193,201 @@ impl Vcs {
         I: IntoIterator<Item = S>,
         S: AsRef<OsStr> + Display,
     {
-        match self {
-            Vcs::Git => git::clone(url, path, args),
-            Vcs::Hg => hg::clone(url, path, args),
-            Vcs::Darcs => darcs::clone(url, path, args),
-            Vcs::Pijul => pijul::clone(url, path, args),
-        }
+        let args: Vec<OsString> = args.into_iter().map(|arg| arg.as_ref().into()).collect();
+        self.0.clone(url.as_ref(), path.as_ref(), &args)
+    }
+
+    /// Download the changes from the default remote without touching the working tree.
+    pub fn do_fetch<P: AsRef<Path>>(self, path: P) -> Result<()> {
+        self.0.fetch(path.as_ref())
+    }
+
+    /// Bring the current branch up to date with the fetched changes,
+    /// without creating any merge.
+    pub fn do_pull<P: AsRef<Path>>(self, path: P) -> Result<()> {
+        self.0.pull(path.as_ref())
     }
 
     pub fn get_remote_url<P: AsRef<Path>>(self, path: P) -> Result<Option<String>> {
-        match self {
-            Vcs::Git => git::get_remote_url(path),
-            Vcs::Hg => hg::get_remote_url(path),
-            _ => Err(anyhow!("This VCS has not supported yet")),
-        }
+        self.0.get_remote_url(path.as_ref())
     }
 
     pub fn set_remote_url(self, path: &Path, url: &str) -> Result<()> {
-        match self {
-            Vcs::Git => git::set_remote(path, url),
-            _ => Err(anyhow!("This VCS has not supported yet")),
-        }
+        self.0.set_remote_url(path, url)
+    }
+
+    /// Returns the currently checked out commit, or `None` if there are no commits yet.
+    pub fn head_commit<P: AsRef<Path>>(self, path: P) -> Result<Option<Commit>> {
+        self.0.head_commit(path.as_ref())
+    }
+
+    /// Returns the name of the default branch of the remote.
+    pub fn default_branch<P: AsRef<Path>>(self, path: P) -> Result<Option<String>> {
+        self.0.default_branch(path.as_ref())
+    }
+
+    /// Returns the description of the repository set by the user, if any.
+    pub fn description<P: AsRef<Path>>(self, path: P) -> Result<Option<String>> {
+        self.0.description(path.as_ref())
+    }
+
+    /// Returns the current state of the working tree.
+    pub fn status<P: AsRef<Path>>(self, path: P) -> Result<Status> {
+        self.0.status(path.as_ref())
+    }
+
+    /// Returns the name of the current branch.
+    pub fn current_branch<P: AsRef<Path>>(self, path: P) -> Result<Option<String>> {
+        self.0.current_branch(path.as_ref())
+    }
+
+    /// Returns whether the working tree has uncommitted changes or untracked files.
+    pub fn is_dirty<P: AsRef<Path>>(self, path: P) -> Result<bool> {
+        self.status(path).map(|status| status.is_dirty())
+    }
+}
+
+impl PartialEq for Vcs {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
+    }
+}
+
+impl Eq for Vcs {}
+
+impl fmt::Debug for Vcs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Vcs").field(&self.name()).finish()
+    }
+}
+
+/// A commit (or changeset, patch) recorded in the repository.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commit {
+    /// Identifier of the commit, e.g. the commit hash.
+    pub id: String,
+    /// Time when the commit was recorded.
+    pub time: DateTime<FixedOffset>,
+}
+
+/// State of a working tree.
+///
+/// The fields which the VCS cannot determine are left as `None`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Status {
+    /// Name of the current branch (or channel).
+    pub branch: Option<String>,
+    /// Number of modified, added or removed entries.
+    pub modified: usize,
+    /// Number of untracked entries.
+    pub untracked: usize,
+    /// Whether the current branch has the upstream to synchronize with, if known.
+    pub has_upstream: Option<bool>,
+    /// Number of local commits not contained in the upstream.
+    pub ahead: Option<usize>,
+    /// Number of upstream commits not contained in the local branch.
+    pub behind: Option<usize>,
+}
+
+impl Status {
+    pub fn is_dirty(&self) -> bool {
+        self.modified > 0 || self.untracked > 0
     }
 }
 
 pub fn detect_from_path<P: AsRef<Path>>(path: P) -> Option<Vcs> {
-    [".git", ".hg", "_darcs", ".pijul"]
-        .iter()
-        .find(|vcs| path.as_ref().join(vcs).exists())
-        .and_then(|s| s.skip(1).parse().ok())
+    Vcs::backends()
+        .into_iter()
+        .find(|vcs| vcs.0.detect(path.as_ref()))
 }
 
 impl FromStr for Vcs {
     type Err = String;
     fn from_str(s: &str) -> ::std::result::Result<Vcs, String> {
-        match s {
-            "git" => Ok(Vcs::Git),
-            "hg" => Ok(Vcs::Hg),
-            "darcs" => Ok(Vcs::Darcs),
-            "pijul" => Ok(Vcs::Pijul),
-            s => Err(format!("{} is invalid string", s)),
-        }
+        let backends = Vcs::backends();
+        backends
+            .iter()
+            .find(|vcs| vcs.name().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<_> = backends.iter().map(|vcs| vcs.name()).collect();
+                format!("{} is invalid string (available: {})", s, names.join(", "))
+            })
     }
 }
 
 impl fmt::Display for Vcs {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Git => f.write_str("git"),
-            Self::Hg => f.write_str("hg"),
-            Self::Darcs => f.write_str("darcs"),
-            Self::Pijul => f.write_str("pijul"),
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for Vcs {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Vcs {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // An unknown name is kept as is, so that the entry survives loading and saving.
+        let name = String::deserialize(deserializer)?;
+        Ok(Vcs::from_name(&name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fossil;
+
+    impl VcsBackend for Fossil {
+        fn name(&self) -> &'static str {
+            "fossil"
+        }
+
+        fn detect(&self, path: &Path) -> bool {
+            path.join(".fslckout").exists()
+        }
+
+        fn init(&self, _path: &Path) -> Result<()> {
+            Ok(())
+        }
+
+        fn clone(&self, _url: &str, _path: &Path, _args: &[OsString]) -> Result<()> {
+            Ok(())
         }
     }
+
+    #[test]
+    fn register_custom_backend() {
+        let fossil = Vcs::register(Fossil).unwrap();
+        assert_eq!("fossil".parse::<Vcs>(), Ok(fossil));
+        assert_eq!(serde_json::to_string(&fossil).unwrap(), "\"fossil\"");
+        assert!(fossil.status(".").unwrap_err().is::<Unsupported>());
+        assert!(Vcs::register(Fossil).is_err());
+    }
+
+    #[test]
+    fn detect_colocated_jj_repository() {
+        let path = std::env::temp_dir().join(format!("rhq-jj-{}", std::process::id()));
+        std::fs::create_dir_all(path.join(".git")).unwrap();
+        assert_eq!(detect_from_path(&path), Some(Vcs::GIT));
+        std::fs::create_dir_all(path.join(".jj")).unwrap();
+        assert_eq!(detect_from_path(&path), Some(Vcs::JJ));
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn parse_builtin_backends() {
+        assert_eq!("git".parse::<Vcs>(), Ok(Vcs::GIT));
+        assert_eq!("Hg".parse::<Vcs>(), Ok(Vcs::HG));
+        assert_eq!("jj".parse::<Vcs>(), Ok(Vcs::JJ));
+        assert!("svn".parse::<Vcs>().is_err());
+        assert_eq!(
+            serde_json::from_str::<Vcs>("\"pijul\"").unwrap(),
+            Vcs::PIJUL
+        );
+    }
 }
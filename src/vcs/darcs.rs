@@ -1,6 +1,14 @@
-use crate::util::process;
+use crate::{
+    util::{self, process},
+    vcs::{Status, VcsBackend},
+};
 use anyhow::{anyhow, Result};
-use std::{ffi::OsStr, path::Path};
+use std::{
+    ffi::{OsStr, OsString},
+    fs,
+    io::Write,
+    path::Path,
+};
 
 pub fn initialize<P>(path: P) -> Result<()>
 where
@@ -31,7 +39,7 @@ where
     process::inherit("darcs")
         .arg("clone")
         .args(args)
-        .args(&[url.as_ref(), &path])
+        .args([url.as_ref(), &path])
         .status()
         .map_err(Into::into)
         .and_then(|st| match st.code() {
@@ -42,3 +50,111 @@ where
             )),
         })
 }
+
+pub fn get_remote_url<P: AsRef<Path>>(path: P) -> Result<Option<String>> {
+    let defaultrepo = path
+        .as_ref()
+        .join("_darcs")
+        .join("prefs")
+        .join("defaultrepo");
+    if !defaultrepo.is_file() {
+        return Ok(None);
+    }
+    Ok(parse_defaultrepo(&fs::read_to_string(defaultrepo)?))
+}
+
+pub fn set_remote_url<P: AsRef<Path>>(path: P, url: &str) -> Result<()> {
+    let defaultrepo = path
+        .as_ref()
+        .join("_darcs")
+        .join("prefs")
+        .join("defaultrepo");
+    util::write_content(defaultrepo, |f| writeln!(f, "{}", url).map_err(Into::into))
+}
+
+/// Extract the default remote from the content of `_darcs/prefs/defaultrepo`.
+fn parse_defaultrepo(s: &str) -> Option<String> {
+    s.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(ToOwned::to_owned)
+}
+
+pub fn status<P: AsRef<Path>>(path: P) -> Result<Status> {
+    // `darcs whatsnew` exits with 1 when there are no changes.
+    let output = process::piped("darcs")
+        .args(["whatsnew", "--summary", "--look-for-adds"])
+        .current_dir(path)
+        .output()?;
+    let mut status = Status::default();
+    match output.status.code() {
+        Some(0) => {
+            for line in String::from_utf8_lossy(&output.stdout).lines() {
+                // Files found by `--look-for-adds` are marked with a lowercase 'a'.
+                if line.starts_with("a ") {
+                    status.untracked += 1;
+                } else if !line.trim().is_empty() {
+                    status.modified += 1;
+                }
+            }
+            Ok(status)
+        }
+        Some(1) => Ok(status),
+        _ => Err(anyhow!("darcs: failed to get the status of working tree")),
+    }
+}
+
+/// Backend of Darcs.
+///
+/// Fetching and pulling are not supported, since Darcs has no way to download
+/// the patches without applying them, and `darcs pull` merges them into the working tree.
+#[derive(Debug)]
+pub struct Darcs;
+
+impl VcsBackend for Darcs {
+    fn name(&self) -> &'static str {
+        "darcs"
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        path.join("_darcs").exists()
+    }
+
+    fn init(&self, path: &Path) -> Result<()> {
+        self::initialize(path)
+    }
+
+    fn clone(&self, url: &str, path: &Path, args: &[OsString]) -> Result<()> {
+        self::clone(url, path, args)
+    }
+
+    fn get_remote_url(&self, path: &Path) -> Result<Option<String>> {
+        self::get_remote_url(path)
+    }
+
+    fn set_remote_url(&self, path: &Path, url: &str) -> Result<()> {
+        self::set_remote_url(path, url)
+    }
+
+    fn status(&self, path: &Path) -> Result<Status> {
+        self::status(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_defaultrepo;
+
+    #[test]
+    fn defaultrepo() {
+        assert_eq!(
+            parse_defaultrepo("https://hub.darcs.net/owner/repo\n"),
+            Some("https://hub.darcs.net/owner/repo".to_owned())
+        );
+        assert_eq!(
+            parse_defaultrepo("\nuser@example.com:repo\n"),
+            Some("user@example.com:repo".to_owned())
+        );
+        assert_eq!(parse_defaultrepo(" \n"), None);
+    }
+}
@@ -1,6 +1,16 @@
-use crate::util::process;
+use crate::{
+    util::process,
+    vcs::{Commit, Status, VcsBackend},
+};
 use anyhow::{anyhow, Result};
-use std::{ffi::OsStr, path::Path};
+use chrono::DateTime;
+use std::{
+    ffi::{OsStr, OsString},
+    path::Path,
+};
+
+#[cfg(feature = "native-git")]
+mod native;
 
 pub fn init<P: AsRef<Path>>(path: P) -> Result<()> {
     process::inherit("git")
@@ -27,7 +37,7 @@ where
     let path = format!("{}", path.as_ref().display());
     process::inherit("git")
         .arg("clone")
-        .args(&[url.as_ref(), &path])
+        .args([url.as_ref(), &path])
         .args(args)
         .status()
         .map_err(Into::into)
@@ -40,14 +50,27 @@ where
         })
 }
 
+pub fn fetch<P: AsRef<Path>>(path: P) -> Result<()> {
+    process::run("git", path, &["fetch", "--quiet"])
+}
+
+pub fn merge_ff_only<P: AsRef<Path>>(path: P) -> Result<()> {
+    process::run(
+        "git",
+        path,
+        &["merge", "--ff-only", "--quiet", "@{upstream}"],
+    )
+}
+
 pub fn get_remote_url<P: AsRef<Path>>(repo_path: P) -> Result<Option<String>> {
-    // 1. get current branch name.
+    // 1. get current branch name, which exists even if there are no commits yet.
     let output = process::piped("git")
         .current_dir(&repo_path)
-        .args(&["rev-parse", "--abbrev-ref", "HEAD"])
+        .args(["symbolic-ref", "--quiet", "--short", "HEAD"])
         .output()?;
     if !output.status.success() {
-        return Err(anyhow!("failed to get branch name"));
+        // detached HEAD
+        return Ok(None);
     }
     let branch = String::from_utf8_lossy(&output.stdout).trim().to_owned();
 
@@ -55,7 +78,7 @@ pub fn get_remote_url<P: AsRef<Path>>(repo_path: P) -> Result<Option<String>> {
     let arg = format!("{}@{{upstream}}", branch);
     let output = process::piped("git")
         .current_dir(&repo_path)
-        .args(&["rev-parse", "--abbrev-ref", &arg])
+        .args(["rev-parse", "--abbrev-ref", &arg])
         .output()?;
     if !output.status.success() {
         return Ok(None);
@@ -68,13 +91,13 @@ pub fn get_remote_url<P: AsRef<Path>>(repo_path: P) -> Result<Option<String>> {
     // 3. get remote URL of upstream ref
     let output = process::piped("git")
         .current_dir(repo_path)
-        .args(&["remote", "get-url", &upstream])
+        .args(["remote", "get-url", &upstream])
         .output()?;
     if !output.status.success() {
         return Ok(None);
     }
     let url = String::from_utf8_lossy(&output.stdout).trim().to_owned();
-    if url == "" {
+    if url.is_empty() {
         Ok(None)
     } else {
         Ok(Some(url))
@@ -83,7 +106,7 @@ pub fn get_remote_url<P: AsRef<Path>>(repo_path: P) -> Result<Option<String>> {
 
 pub fn set_remote<P: AsRef<Path>>(path: P, url: &str) -> Result<()> {
     let st = process::piped("git")
-        .args(&["remote", "add", "origin", url])
+        .args(["remote", "add", "origin", url])
         .current_dir(path)
         .status()?;
     match st.code() {
@@ -94,3 +117,205 @@ pub fn set_remote<P: AsRef<Path>>(path: P, url: &str) -> Result<()> {
         )),
     }
 }
+
+pub fn head_commit<P: AsRef<Path>>(path: P) -> Result<Option<Commit>> {
+    let output = process::piped("git")
+        .args(["log", "-1", "--format=%H %cI"])
+        .current_dir(path)
+        .output()?;
+    if !output.status.success() {
+        // no commits yet
+        return Ok(None);
+    }
+    let output = String::from_utf8_lossy(&output.stdout);
+    match output.trim().split_once(' ') {
+        Some((id, time)) => Ok(Some(Commit {
+            id: id.to_owned(),
+            time: DateTime::parse_from_rfc3339(time)?,
+        })),
+        None => Ok(None),
+    }
+}
+
+pub fn default_branch<P: AsRef<Path>>(path: P) -> Result<Option<String>> {
+    // `refs/remotes/origin/HEAD` is set by `git clone` and `git remote set-head`.
+    let output = process::piped("git")
+        .args([
+            "symbolic-ref",
+            "--quiet",
+            "--short",
+            "refs/remotes/origin/HEAD",
+        ])
+        .current_dir(path)
+        .output()?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let branch = String::from_utf8_lossy(&output.stdout);
+    Ok(branch
+        .trim()
+        .strip_prefix("origin/")
+        .filter(|branch| !branch.is_empty())
+        .map(ToOwned::to_owned))
+}
+
+pub fn description<P: AsRef<Path>>(path: P) -> Result<Option<String>> {
+    let path = path.as_ref().join(".git").join("description");
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let description = std::fs::read_to_string(path)?;
+    let description = description.trim();
+    // the placeholder written by `git init`
+    if description.is_empty() || description.starts_with("Unnamed repository;") {
+        Ok(None)
+    } else {
+        Ok(Some(description.to_owned()))
+    }
+}
+
+pub fn status<P: AsRef<Path>>(path: P) -> Result<Status> {
+    let output = process::piped("git")
+        .args(["status", "--porcelain=v2", "--branch"])
+        .current_dir(path)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!("git: failed to get the status of working tree"));
+    }
+    Ok(parse_status(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_status(s: &str) -> Status {
+    let mut status = Status::default();
+    for line in s.lines() {
+        if let Some(head) = line.strip_prefix("# branch.head ") {
+            if head != "(detached)" {
+                status.branch = Some(head.to_owned());
+            }
+            // `# branch.upstream` follows if the branch has the upstream.
+            status.has_upstream = Some(false);
+        } else if line.starts_with("# branch.upstream ") {
+            status.has_upstream = Some(true);
+        } else if let Some(ab) = line.strip_prefix("# branch.ab ") {
+            let mut counts = ab
+                .split_whitespace()
+                .map(|n| n.trim_start_matches(['+', '-']).parse().ok());
+            status.ahead = counts.next().flatten();
+            status.behind = counts.next().flatten();
+        } else if line.starts_with("? ") {
+            status.untracked += 1;
+        } else if line.starts_with("1 ") || line.starts_with("2 ") || line.starts_with("u ") {
+            status.modified += 1;
+        }
+    }
+    status
+}
+
+/// Backend of Git.
+#[derive(Debug)]
+pub struct Git;
+
+impl VcsBackend for Git {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        path.join(".git").exists()
+    }
+
+    fn init(&self, path: &Path) -> Result<()> {
+        self::init(path)
+    }
+
+    fn clone(&self, url: &str, path: &Path, args: &[OsString]) -> Result<()> {
+        self::clone(url, path, args)
+    }
+
+    fn get_remote_url(&self, path: &Path) -> Result<Option<String>> {
+        #[cfg(feature = "native-git")]
+        if let Some(value) = native::try_run(|| native::get_remote_url(path)) {
+            return Ok(value);
+        }
+        self::get_remote_url(path)
+    }
+
+    fn set_remote_url(&self, path: &Path, url: &str) -> Result<()> {
+        self::set_remote(path, url)
+    }
+
+    fn fetch(&self, path: &Path) -> Result<()> {
+        self::fetch(path)
+    }
+
+    fn pull(&self, path: &Path) -> Result<()> {
+        self::merge_ff_only(path)
+    }
+
+    fn status(&self, path: &Path) -> Result<Status> {
+        #[cfg(feature = "native-git")]
+        if let Some(value) = native::try_run(|| native::status(path)) {
+            return Ok(value);
+        }
+        self::status(path)
+    }
+
+    fn head_commit(&self, path: &Path) -> Result<Option<Commit>> {
+        #[cfg(feature = "native-git")]
+        if let Some(value) = native::try_run(|| native::head_commit(path)) {
+            return Ok(value);
+        }
+        self::head_commit(path)
+    }
+
+    fn default_branch(&self, path: &Path) -> Result<Option<String>> {
+        #[cfg(feature = "native-git")]
+        if let Some(value) = native::try_run(|| native::default_branch(path)) {
+            return Ok(value);
+        }
+        self::default_branch(path)
+    }
+
+    fn description(&self, path: &Path) -> Result<Option<String>> {
+        self::description(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_with_upstream() {
+        let s = "\
+# branch.oid 0123456789abcdef0123456789abcdef01234567
+# branch.head main
+# branch.upstream origin/main
+# branch.ab +2 -1
+1 .M N... 100644 100644 100644 0123 4567 src/lib.rs
+2 R. N... 100644 100644 100644 0123 4567 R100 src/new.rs\tsrc/old.rs
+? untracked.txt
+";
+        let status = parse_status(s);
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.modified, 2);
+        assert_eq!(status.untracked, 1);
+        assert_eq!(status.has_upstream, Some(true));
+        assert_eq!(status.ahead, Some(2));
+        assert_eq!(status.behind, Some(1));
+    }
+
+    #[test]
+    fn status_detached_without_upstream() {
+        let s = "\
+# branch.oid 0123456789abcdef0123456789abcdef01234567
+# branch.head (detached)
+";
+        let status = parse_status(s);
+        assert_eq!(status.branch, None);
+        assert!(!status.is_dirty());
+        assert_eq!(status.has_upstream, Some(false));
+        assert_eq!(status.ahead, None);
+        assert_eq!(status.behind, None);
+    }
+}
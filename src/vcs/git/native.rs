@@ -0,0 +1,231 @@
+//! In-process implementation of the read-only Git operations, using libgit2.
+//!
+//! These avoid spawning `git` processes for every repository while scanning.
+//! Any failure falls back to the `git` command, and setting `RHQ_GIT_BACKEND=cli`
+//! disables this implementation entirely.
+
+use crate::vcs::{Commit, Status};
+use chrono::{FixedOffset, TimeZone};
+use git2::{BranchType, ErrorCode, Repository, StatusOptions};
+use std::{env, path::Path};
+
+/// Returns the result of the in-process implementation, or `None` to fall back to the `git` command.
+pub fn try_run<T>(f: impl FnOnce() -> Result<T, git2::Error>) -> Option<T> {
+    if env::var_os("RHQ_GIT_BACKEND").is_some_and(|backend| backend == "cli") {
+        return None;
+    }
+    f().map_err(|err| log::debug!("falling back to the git command: {}", err))
+        .ok()
+}
+
+/// Returns the name of the current branch, even if it has no commits yet.
+fn current_branch(repo: &Repository) -> Result<Option<String>, git2::Error> {
+    let head = repo.find_reference("HEAD")?;
+    Ok(head
+        .symbolic_target()
+        .and_then(|target| target.strip_prefix("refs/heads/"))
+        .map(ToOwned::to_owned))
+}
+
+pub fn get_remote_url(path: &Path) -> Result<Option<String>, git2::Error> {
+    let repo = Repository::open(path)?;
+    let refname = match current_branch(&repo)? {
+        Some(branch) => format!("refs/heads/{}", branch),
+        None => return Ok(None),
+    };
+    let remote = match repo.branch_upstream_remote(&refname) {
+        Ok(remote) => remote,
+        Err(err) if err.code() == ErrorCode::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let remote = match remote.as_str() {
+        // the upstream is a local branch
+        Some(".") | None => return Ok(None),
+        Some(name) => repo.find_remote(name)?,
+    };
+    Ok(remote
+        .url()
+        .filter(|url| !url.is_empty())
+        .map(ToOwned::to_owned))
+}
+
+pub fn status(path: &Path) -> Result<Status, git2::Error> {
+    let repo = Repository::open(path)?;
+    let mut status = Status {
+        branch: current_branch(&repo)?,
+        ..Default::default()
+    };
+
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .include_ignored(false);
+    for entry in repo.statuses(Some(&mut options))?.iter() {
+        let flags = entry.status();
+        if flags.is_wt_new() {
+            status.untracked += 1;
+        } else if !flags.is_empty() && !flags.is_ignored() {
+            status.modified += 1;
+        }
+    }
+
+    status.has_upstream = Some(false);
+    if let Some(ref branch) = status.branch {
+        let local = match repo.find_branch(branch, BranchType::Local) {
+            Ok(local) => local,
+            // no commits yet
+            Err(err) if err.code() == ErrorCode::NotFound => return Ok(status),
+            Err(err) => return Err(err),
+        };
+        if let Ok(upstream) = local.upstream() {
+            status.has_upstream = Some(true);
+            if let (Some(local), Some(upstream)) = (local.get().target(), upstream.get().target()) {
+                let (ahead, behind) = repo.graph_ahead_behind(local, upstream)?;
+                status.ahead = Some(ahead);
+                status.behind = Some(behind);
+            }
+        }
+    }
+
+    Ok(status)
+}
+
+pub fn head_commit(path: &Path) -> Result<Option<Commit>, git2::Error> {
+    let repo = Repository::open(path)?;
+    let head = match repo.head() {
+        Ok(head) => head,
+        Err(err) if err.code() == ErrorCode::UnbornBranch => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let commit = head.peel_to_commit()?;
+    let time = commit.time();
+    let time = FixedOffset::east_opt(time.offset_minutes() * 60)
+        .and_then(|offset| offset.timestamp_opt(time.seconds(), 0).single())
+        .ok_or_else(|| git2::Error::from_str("invalid commit time"))?;
+    Ok(Some(Commit {
+        id: commit.id().to_string(),
+        time,
+    }))
+}
+
+pub fn default_branch(path: &Path) -> Result<Option<String>, git2::Error> {
+    let repo = Repository::open(path)?;
+    let head = match repo.find_reference("refs/remotes/origin/HEAD") {
+        Ok(head) => head,
+        Err(err) if err.code() == ErrorCode::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    Ok(head
+        .symbolic_target()
+        .and_then(|target| target.strip_prefix("refs/remotes/origin/"))
+        .filter(|branch| !branch.is_empty())
+        .map(ToOwned::to_owned))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcs::git as cli;
+    use std::{fs, path::PathBuf, process::Command};
+
+    /// Temporary directory removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = env::temp_dir().join(format!("rhq-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let output = Command::new("git")
+            .args(["-c", "user.name=rhq", "-c", "user.email=rhq@example.com"])
+            .args(["-c", "init.defaultBranch=main"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?}: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    fn assert_same_as_cli(path: &Path) {
+        assert_eq!(status(path).unwrap(), cli::status(path).unwrap());
+        assert_eq!(
+            get_remote_url(path).unwrap(),
+            cli::get_remote_url(path).unwrap()
+        );
+        assert_eq!(head_commit(path).unwrap(), cli::head_commit(path).unwrap());
+        assert_eq!(
+            default_branch(path).unwrap(),
+            cli::default_branch(path).unwrap()
+        );
+    }
+
+    #[test]
+    fn same_as_cli_without_commits() {
+        let dir = TempDir::new("native-empty");
+        git(&dir.0, &["init", "--quiet", "."]);
+        assert_same_as_cli(&dir.0);
+    }
+
+    #[test]
+    fn same_as_cli_with_diverged_clone() {
+        let dir = TempDir::new("native-clone");
+        let (upstream, seed, work) = (
+            dir.0.join("upstream.git"),
+            dir.0.join("seed"),
+            dir.0.join("work"),
+        );
+        git(&dir.0, &["init", "--quiet", "--bare", "upstream.git"]);
+        git(&dir.0, &["clone", "--quiet", "upstream.git", "seed"]);
+        fs::write(seed.join("README"), "rhq\n").unwrap();
+        git(&seed, &["add", "README"]);
+        git(&seed, &["commit", "--quiet", "-m", "initial"]);
+        git(&seed, &["push", "--quiet", "origin", "main"]);
+        assert_same_as_cli(&seed);
+
+        git(&dir.0, &["clone", "--quiet", "upstream.git", "work"]);
+        assert_same_as_cli(&work);
+
+        // one commit ahead and one behind, with a modified and an untracked file
+        git(
+            &seed,
+            &["commit", "--quiet", "--allow-empty", "-m", "upstream"],
+        );
+        git(&seed, &["push", "--quiet", "origin", "main"]);
+        git(
+            &work,
+            &["commit", "--quiet", "--allow-empty", "-m", "local"],
+        );
+        git(&work, &["fetch", "--quiet"]);
+        fs::write(work.join("README"), "modified\n").unwrap();
+        fs::write(work.join("untracked"), "").unwrap();
+        let status = status(&work).unwrap();
+        assert_eq!((status.ahead, status.behind), (Some(1), Some(1)));
+        assert_eq!((status.modified, status.untracked), (1, 1));
+        assert_eq!(
+            get_remote_url(&work).unwrap().map(PathBuf::from),
+            Some(upstream)
+        );
+        assert_same_as_cli(&work);
+
+        // detached HEAD
+        git(&work, &["checkout", "--quiet", "--detach"]);
+        assert_same_as_cli(&work);
+    }
+}
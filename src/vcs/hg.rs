@@ -1,6 +1,15 @@
-use crate::util::process;
+use crate::{
+    util::{self, process},
+    vcs::{Commit, Status, VcsBackend},
+};
 use anyhow::{anyhow, Result};
-use std::{ffi::OsStr, path::Path};
+use chrono::DateTime;
+use std::{
+    ffi::{OsStr, OsString},
+    fs,
+    io::Write,
+    path::Path,
+};
 
 pub fn init<P>(path: P) -> Result<()>
 where
@@ -28,7 +37,7 @@ where
     process::inherit("hg")
         .arg("clone")
         .args(args)
-        .args(&[url.as_ref(), &path])
+        .args([url.as_ref(), &path])
         .status()
         .map_err(Into::into)
         .and_then(|st| match st.code() {
@@ -37,6 +46,15 @@ where
         })
 }
 
+pub fn pull<P: AsRef<Path>>(path: P) -> Result<()> {
+    process::run("hg", path, &["pull", "--quiet"])
+}
+
+pub fn update<P: AsRef<Path>>(path: P) -> Result<()> {
+    // `--check` prevents the update from crossing branches or discarding local changes.
+    process::run("hg", path, &["update", "--check", "--quiet"])
+}
+
 pub fn get_remote_url<P: AsRef<Path>>(repo_path: P) -> Result<Option<String>> {
     // 1. get current branch
     let output = process::piped("hg")
@@ -60,9 +78,236 @@ pub fn get_remote_url<P: AsRef<Path>>(repo_path: P) -> Result<Option<String>> {
         return Ok(None);
     }
     let url = String::from_utf8_lossy(&output.stdout).trim().to_owned();
-    if url == "" {
+    if url.is_empty() {
         Ok(None)
     } else {
         Ok(Some(url))
     }
 }
+
+/// Returns `default` if the repository has the branch, which is checked out by `hg clone`.
+pub fn default_branch<P: AsRef<Path>>(path: P) -> Result<Option<String>> {
+    let output = process::piped("hg")
+        .args(["branches", "--closed", "-T", "{branch}\n"])
+        .current_dir(path)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!("hg: failed to get the list of branches"));
+    }
+    let has_default = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .any(|branch| branch == "default");
+    Ok(if has_default {
+        Some("default".to_owned())
+    } else {
+        None
+    })
+}
+
+pub fn head_commit<P: AsRef<Path>>(path: P) -> Result<Option<Commit>> {
+    let output = process::piped("hg")
+        .args(["log", "-r", ".", "-T", "{rev} {node} {date|rfc3339date}"])
+        .current_dir(path)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!("hg: failed to get the last commit"));
+    }
+    let output = String::from_utf8_lossy(&output.stdout);
+    let mut fields = output.split_whitespace();
+    match (fields.next(), fields.next(), fields.next()) {
+        // the working directory has no parent (revision -1) in an empty repository
+        (Some("-1"), ..) => Ok(None),
+        (Some(_), Some(id), Some(time)) => Ok(Some(Commit {
+            id: id.to_owned(),
+            time: DateTime::parse_from_rfc3339(time)?,
+        })),
+        _ => Ok(None),
+    }
+}
+
+pub fn set_remote_url<P: AsRef<Path>>(path: P, url: &str) -> Result<()> {
+    let hgrc = path.as_ref().join(".hg").join("hgrc");
+    let content = if hgrc.is_file() {
+        fs::read_to_string(&hgrc)?
+    } else {
+        String::new()
+    };
+    let content = set_default_path(&content, url);
+    util::write_content(hgrc, |f| {
+        f.write_all(content.as_bytes()).map_err(Into::into)
+    })
+}
+
+/// Set `default` in the `[paths]` section of the content of `hgrc`, keeping the other lines as is.
+fn set_default_path(hgrc: &str, url: &str) -> String {
+    let entry = format!("default = {}", url);
+    let mut lines: Vec<String> = hgrc.lines().map(ToOwned::to_owned).collect();
+
+    let is_section = |line: &str| line.trim_start().starts_with('[');
+    match lines.iter().position(|line| line.trim() == "[paths]") {
+        Some(start) => {
+            let end = lines[start + 1..]
+                .iter()
+                .position(|line| is_section(line))
+                .map_or(lines.len(), |i| start + 1 + i);
+            let default = lines[start + 1..end].iter().position(|line| {
+                line.split_once('=')
+                    .is_some_and(|(key, _)| key.trim() == "default")
+            });
+            match default {
+                Some(i) => lines[start + 1 + i] = entry,
+                None => lines.insert(start + 1, entry),
+            }
+        }
+        None => {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push("[paths]".to_owned());
+            lines.push(entry);
+        }
+    }
+
+    let mut content = lines.join("\n");
+    content.push('\n');
+    content
+}
+
+pub fn status<P: AsRef<Path>>(path: P) -> Result<Status> {
+    let path = path.as_ref();
+    let mut status = Status::default();
+
+    let output = process::piped("hg")
+        .arg("branch")
+        .current_dir(path)
+        .output()?;
+    if output.status.success() {
+        status.branch = Some(String::from_utf8_lossy(&output.stdout).trim().to_owned());
+    }
+
+    let output = process::piped("hg")
+        .arg("status")
+        .current_dir(path)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!("hg: failed to get the status of working tree"));
+    }
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if line.starts_with("? ") {
+            status.untracked += 1;
+        } else if !line.is_empty() {
+            status.modified += 1;
+        }
+    }
+
+    let has_upstream = process::piped("hg")
+        .args(["paths", "default"])
+        .current_dir(path)
+        .status()?
+        .success();
+    status.has_upstream = Some(has_upstream);
+    if has_upstream {
+        // Draft changesets on the current branch have not been pushed yet, while
+        // the public ones which are not the ancestors of the working directory
+        // have been pulled but not checked out.
+        status.ahead = count_revisions(path, "draft() and branch(.)")?;
+        status.behind = count_revisions(path, "public() and branch(.) and not ::.")?;
+    }
+
+    Ok(status)
+}
+
+fn count_revisions(path: &Path, revset: &str) -> Result<Option<usize>> {
+    let output = process::piped("hg")
+        .args(["log", "-r", revset, "-T", "{node}\n"])
+        .current_dir(path)
+        .output()?;
+    Ok(if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).lines().count())
+    } else {
+        None
+    })
+}
+
+/// Backend of Hg.
+#[derive(Debug)]
+pub struct Hg;
+
+impl VcsBackend for Hg {
+    fn name(&self) -> &'static str {
+        "hg"
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        path.join(".hg").exists()
+    }
+
+    fn init(&self, path: &Path) -> Result<()> {
+        self::init(path)
+    }
+
+    fn clone(&self, url: &str, path: &Path, args: &[OsString]) -> Result<()> {
+        self::clone(url, path, args)
+    }
+
+    fn get_remote_url(&self, path: &Path) -> Result<Option<String>> {
+        self::get_remote_url(path)
+    }
+
+    fn set_remote_url(&self, path: &Path, url: &str) -> Result<()> {
+        self::set_remote_url(path, url)
+    }
+
+    fn fetch(&self, path: &Path) -> Result<()> {
+        self::pull(path)
+    }
+
+    fn pull(&self, path: &Path) -> Result<()> {
+        self::update(path)
+    }
+
+    fn status(&self, path: &Path) -> Result<Status> {
+        self::status(path)
+    }
+
+    fn head_commit(&self, path: &Path) -> Result<Option<Commit>> {
+        self::head_commit(path)
+    }
+
+    fn default_branch(&self, path: &Path) -> Result<Option<String>> {
+        self::default_branch(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::set_default_path;
+
+    #[test]
+    fn add_paths_section() {
+        assert_eq!(
+            set_default_path("", "https://example.com/repo"),
+            "[paths]\ndefault = https://example.com/repo\n"
+        );
+        assert_eq!(
+            set_default_path("[ui]\nusername = me\n", "https://example.com/repo"),
+            "[ui]\nusername = me\n\n[paths]\ndefault = https://example.com/repo\n"
+        );
+    }
+
+    #[test]
+    fn replace_default_path() {
+        let hgrc =
+            "[paths]\nupstream = ssh://a/b\ndefault = ssh://old/repo\n\n[ui]\nusername = me\n";
+        assert_eq!(
+            set_default_path(hgrc, "ssh://new/repo"),
+            "[paths]\nupstream = ssh://a/b\ndefault = ssh://new/repo\n\n[ui]\nusername = me\n"
+        );
+
+        let hgrc = "[paths]\nupstream = ssh://a/b\n[ui]\ndefault = x\n";
+        assert_eq!(
+            set_default_path(hgrc, "ssh://new/repo"),
+            "[paths]\ndefault = ssh://new/repo\nupstream = ssh://a/b\n[ui]\ndefault = x\n"
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{
+    util::process,
+    vcs::{Commit, Status, VcsBackend},
+};
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use std::{
+    ffi::{OsStr, OsString},
+    path::Path,
+};
+
+pub fn init<P: AsRef<Path>>(path: P) -> Result<()> {
+    // Colocate the Git repository, so that the working tree can also be used by Git tools.
+    process::inherit("jj")
+        .args(["git", "init", "--colocate"])
+        .arg(path.as_ref().as_os_str())
+        .status()
+        .map_err(Into::into)
+        .and_then(|st| match st.code() {
+            Some(0) => Ok(()),
+            st => Err(anyhow!("command 'jj' is exited with return code {:?}.", st)),
+        })
+}
+
+pub fn clone<P, U, I, S>(url: U, path: P, args: I) -> Result<()>
+where
+    P: AsRef<Path>,
+    U: AsRef<str>,
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let path = format!("{}", path.as_ref().display());
+    // Colocated as well as the repositories created by `init`.
+    process::inherit("jj")
+        .args(["git", "clone", "--colocate"])
+        .args(args)
+        .args([url.as_ref(), &path])
+        .status()
+        .map_err(Into::into)
+        .and_then(|st| match st.code() {
+            Some(0) => Ok(()),
+            st => Err(anyhow!("command 'jj' is exited with return code {:?}.", st)),
+        })
+}
+
+pub fn fetch<P: AsRef<Path>>(path: P) -> Result<()> {
+    process::run("jj", path, &["git", "fetch", "--quiet"])
+}
+
+pub fn get_remote_url<P: AsRef<Path>>(path: P) -> Result<Option<String>> {
+    let output = process::piped("jj")
+        .args(["git", "remote", "list"])
+        .current_dir(path)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!("jj: failed to list the remotes"));
+    }
+    Ok(parse_remote_list(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Pick the URL of `origin`, or the first remote, from the output of `jj git remote list`.
+fn parse_remote_list(s: &str) -> Option<String> {
+    let remotes: Vec<(&str, &str)> = s
+        .lines()
+        .filter_map(|line| line.trim().split_once(char::is_whitespace))
+        .map(|(name, url)| (name, url.trim()))
+        .filter(|(_, url)| !url.is_empty())
+        .collect();
+    remotes
+        .iter()
+        .find(|(name, _)| *name == "origin")
+        .or_else(|| remotes.first())
+        .map(|(_, url)| (*url).to_owned())
+}
+
+pub fn set_remote<P: AsRef<Path>>(path: P, url: &str) -> Result<()> {
+    process::run("jj", path, &["git", "remote", "add", "origin", url])
+}
+
+pub fn status<P: AsRef<Path>>(path: P) -> Result<Status> {
+    // Every file is tracked by jj, so the changes in the working-copy commit are
+    // counted as modified and there are no untracked files.
+    let output = process::piped("jj")
+        .args(["diff", "--summary", "-r", "@"])
+        .current_dir(path)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!("jj: failed to get the status of working copy"));
+    }
+    let modified = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .count();
+    Ok(Status {
+        modified,
+        ..Default::default()
+    })
+}
+
+pub fn head_commit<P: AsRef<Path>>(path: P) -> Result<Option<Commit>> {
+    // The working-copy commit (`@`) is being edited, so its parent is the last commit.
+    let output = process::piped("jj")
+        .args(["log", "--no-graph", "-r", "@-", "-T"])
+        .arg(r#"commit_id ++ " " ++ committer.timestamp().format("%+") ++ "\n""#)
+        .current_dir(path)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!("jj: failed to get the last commit"));
+    }
+    parse_head_commit(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_head_commit(s: &str) -> Result<Option<Commit>> {
+    // The parent of a merge commit is not unique, and the first one is taken.
+    let (id, time) = match s.lines().next().and_then(|line| line.split_once(' ')) {
+        Some(fields) => fields,
+        None => return Ok(None),
+    };
+    // the root commit, which means there are no commits yet
+    if id.chars().all(|c| c == '0') {
+        return Ok(None);
+    }
+    Ok(Some(Commit {
+        id: id.to_owned(),
+        time: DateTime::parse_from_rfc3339(time.trim())?,
+    }))
+}
+
+/// Backend of Jujutsu.
+///
+/// It is detected before Git, since a Jujutsu repository colocated with Git
+/// contains both of `.jj` and `.git`.
+#[derive(Debug)]
+pub struct Jj;
+
+impl VcsBackend for Jj {
+    fn name(&self) -> &'static str {
+        "jj"
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        path.join(".jj").exists()
+    }
+
+    fn init(&self, path: &Path) -> Result<()> {
+        self::init(path)
+    }
+
+    fn clone(&self, url: &str, path: &Path, args: &[OsString]) -> Result<()> {
+        self::clone(url, path, args)
+    }
+
+    fn get_remote_url(&self, path: &Path) -> Result<Option<String>> {
+        self::get_remote_url(path)
+    }
+
+    fn set_remote_url(&self, path: &Path, url: &str) -> Result<()> {
+        self::set_remote(path, url)
+    }
+
+    fn fetch(&self, path: &Path) -> Result<()> {
+        self::fetch(path)
+    }
+
+    fn status(&self, path: &Path) -> Result<Status> {
+        self::status(path)
+    }
+
+    fn head_commit(&self, path: &Path) -> Result<Option<Commit>> {
+        self::head_commit(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_head_commit, parse_remote_list};
+
+    #[test]
+    fn remote_list() {
+        let output =
+            "upstream https://github.com/upstream/repo.git\norigin git@github.com:me/repo.git\n";
+        assert_eq!(
+            parse_remote_list(output),
+            Some("git@github.com:me/repo.git".to_owned())
+        );
+        let output = "upstream https://github.com/upstream/repo.git\n";
+        assert_eq!(
+            parse_remote_list(output),
+            Some("https://github.com/upstream/repo.git".to_owned())
+        );
+        assert_eq!(parse_remote_list(""), None);
+    }
+
+    #[test]
+    fn head_commit() {
+        let output = "0123abcd 2024-01-02T03:04:05+09:00\n";
+        let commit = parse_head_commit(output).unwrap().unwrap();
+        assert_eq!(commit.id, "0123abcd");
+        assert_eq!(commit.time.to_rfc3339(), "2024-01-02T03:04:05+09:00");
+
+        let output = "0000000000000000000000000000000000000000 1970-01-01T00:00:00+00:00\n";
+        assert_eq!(parse_head_commit(output).unwrap(), None);
+    }
+}
@@ -1,6 +1,14 @@
-use crate::util::process;
+use crate::{
+    util::{self, process},
+    vcs::{Status, VcsBackend},
+};
 use anyhow::{anyhow, Result};
-use std::{ffi::OsStr, fs, path::Path};
+use std::{
+    ffi::{OsStr, OsString},
+    fs,
+    io::Write,
+    path::Path,
+};
 
 pub fn init<P>(path: P) -> Result<()>
 where
@@ -32,7 +40,7 @@ where
     process::inherit("pijul")
         .arg("clone")
         .args(args)
-        .args(&[url.as_ref(), &path])
+        .args([url.as_ref(), &path])
         .status()
         .map_err(Into::into)
         .and_then(|st| match st.code() {
@@ -43,3 +51,195 @@ where
             )),
         })
 }
+
+pub fn get_remote_url<P: AsRef<Path>>(path: P) -> Result<Option<String>> {
+    let config = path.as_ref().join(".pijul").join("config");
+    if !config.is_file() {
+        return Ok(None);
+    }
+    parse_config(&fs::read_to_string(config)?)
+}
+
+pub fn set_remote_url<P: AsRef<Path>>(path: P, url: &str) -> Result<()> {
+    let config_path = path.as_ref().join(".pijul").join("config");
+    let mut config: toml::Table = if config_path.is_file() {
+        toml::from_str(&fs::read_to_string(&config_path)?)?
+    } else {
+        toml::Table::new()
+    };
+    config.insert("default_remote".to_owned(), url.into());
+    let content = toml::to_string(&config)?;
+    util::write_content(config_path, |f| {
+        f.write_all(content.as_bytes()).map_err(Into::into)
+    })
+}
+
+/// Extract the default remote from the content of `.pijul/config`.
+///
+/// `default_remote` is either a URL or the name of an entry in `remotes`,
+/// which is a table from names to URLs or a list of `{ name, http | ssh | url }`.
+fn parse_config(s: &str) -> Result<Option<String>> {
+    let config: toml::Table = toml::from_str(s)?;
+    let default = match config.get("default_remote").and_then(|v| v.as_str()) {
+        Some(default) => default,
+        None => return Ok(None),
+    };
+
+    let url_of = |remote: &toml::Value| -> Option<String> {
+        match remote {
+            toml::Value::String(url) => Some(url.clone()),
+            toml::Value::Table(table) => ["http", "ssh", "url"]
+                .iter()
+                .find_map(|key| table.get(*key).and_then(|v| v.as_str()))
+                .map(ToOwned::to_owned),
+            _ => None,
+        }
+    };
+    let named = match config.get("remotes") {
+        Some(toml::Value::Table(remotes)) => remotes.get(default).and_then(url_of),
+        Some(toml::Value::Array(remotes)) => remotes
+            .iter()
+            .find(|remote| remote.get("name").and_then(|v| v.as_str()) == Some(default))
+            .and_then(url_of),
+        _ => None,
+    };
+
+    Ok(Some(named.unwrap_or_else(|| default.to_owned())))
+}
+
+pub fn status<P: AsRef<Path>>(path: P) -> Result<Status> {
+    let path = path.as_ref();
+    let mut status = Status::default();
+
+    let output = process::piped("pijul")
+        .arg("channel")
+        .current_dir(path)
+        .output()?;
+    if output.status.success() {
+        status.branch = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| line.strip_prefix("* "))
+            .map(|channel| channel.trim().to_owned());
+    }
+
+    let output = process::piped("pijul")
+        .args(["diff", "--short", "--untracked"])
+        .current_dir(path)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!("pijul: failed to get the status of working tree"));
+    }
+    parse_diff_short(&String::from_utf8_lossy(&output.stdout), &mut status);
+
+    Ok(status)
+}
+
+/// Count the entries in the output of `pijul diff --short --untracked`.
+fn parse_diff_short(s: &str, status: &mut Status) {
+    for line in s.lines().filter(|line| !line.trim().is_empty()) {
+        // Untracked files are marked with 'U', e.g. `U  new.txt`.
+        if line.starts_with("U ") {
+            status.untracked += 1;
+        } else {
+            status.modified += 1;
+        }
+    }
+}
+
+/// Backend of Pijul.
+///
+/// Fetching and pulling are not supported, since Pijul has no way to download
+/// the changes without applying them, and `pijul pull` merges them into the channel.
+#[derive(Debug)]
+pub struct Pijul;
+
+impl VcsBackend for Pijul {
+    fn name(&self) -> &'static str {
+        "pijul"
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        path.join(".pijul").exists()
+    }
+
+    fn init(&self, path: &Path) -> Result<()> {
+        self::init(path)
+    }
+
+    fn clone(&self, url: &str, path: &Path, args: &[OsString]) -> Result<()> {
+        self::clone(url, path, args)
+    }
+
+    fn get_remote_url(&self, path: &Path) -> Result<Option<String>> {
+        self::get_remote_url(path)
+    }
+
+    fn set_remote_url(&self, path: &Path, url: &str) -> Result<()> {
+        self::set_remote_url(path, url)
+    }
+
+    fn status(&self, path: &Path) -> Result<Status> {
+        self::status(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_config, parse_diff_short};
+    use crate::vcs::Status;
+
+    #[test]
+    fn diff_short() {
+        let mut status = Status::default();
+        parse_diff_short("M  src/main.rs\nA  src/lib.rs\nU  notes.txt\n", &mut status);
+        assert_eq!(status.modified, 2);
+        assert_eq!(status.untracked, 1);
+    }
+
+    #[test]
+    fn dirty_with_only_untracked_files() {
+        let mut status = Status::default();
+        parse_diff_short("U  notes.txt\n", &mut status);
+        assert!(status.is_dirty());
+
+        let mut status = Status::default();
+        parse_diff_short("\n", &mut status);
+        assert!(!status.is_dirty());
+    }
+
+    #[test]
+    fn default_remote_url() {
+        let config = "default_remote = \"https://nest.pijul.com/owner/repo\"\n";
+        assert_eq!(
+            parse_config(config).unwrap(),
+            Some("https://nest.pijul.com/owner/repo".to_owned())
+        );
+        assert_eq!(parse_config("[hooks]\nrecord = []\n").unwrap(), None);
+    }
+
+    #[test]
+    fn default_remote_name() {
+        let config = r#"
+default_remote = "origin"
+
+[remotes]
+origin = "me@nest.pijul.com:owner/repo"
+"#;
+        assert_eq!(
+            parse_config(config).unwrap(),
+            Some("me@nest.pijul.com:owner/repo".to_owned())
+        );
+
+        let config = r#"
+default_remote = "origin"
+
+[[remotes]]
+name = "origin"
+http = "https://nest.pijul.com/owner/repo"
+"#;
+        assert_eq!(
+            parse_config(config).unwrap(),
+            Some("https://nest.pijul.com/owner/repo".to_owned())
+        );
+    }
+}
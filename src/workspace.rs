@@ -1,16 +1,22 @@
 use crate::{
     cache::Cache,
     config::Config,
+    pool::Pool,
     printer::Printer,
     query::Query,
-    remote::Remote,
+    remote::{Protocol, Remote},
     repository::Repository,
+    sort::SortKey,
+    util,
     vcs::{self, Vcs},
 };
-use anyhow::Result;
+use anyhow::{anyhow, Context as _, Result};
+use chrono::Local;
 use glob::Pattern;
 use std::{
+    borrow::Cow,
     fmt::Arguments,
+    fs,
     path::{Path, PathBuf},
 };
 use walkdir::{DirEntry, WalkDir};
@@ -19,6 +25,7 @@ pub struct Workspace<'ws> {
     pub cache: &'ws mut Cache,
     pub config: &'ws Config,
     printer: Printer,
+    jobs: usize,
 }
 
 impl<'ws> Workspace<'ws> {
@@ -27,6 +34,7 @@ impl<'ws> Workspace<'ws> {
             cache,
             config,
             printer: Printer::default(),
+            jobs: config.jobs,
         }
     }
 
@@ -34,6 +42,16 @@ impl<'ws> Workspace<'ws> {
         self.printer.verbose = verbose;
     }
 
+    /// Set the maximum number of repositories processed concurrently.
+    pub fn set_jobs(&mut self, jobs: usize) {
+        self.jobs = jobs;
+    }
+
+    /// Returns a worker pool for operations over multiple repositories.
+    pub fn pool(&self) -> Pool {
+        Pool::new(self.jobs)
+    }
+
     pub fn print(&self, args: Arguments) {
         self.printer.print(args)
     }
@@ -51,8 +69,34 @@ impl<'ws> Workspace<'ws> {
         root: P,
         depth: Option<usize>,
     ) -> Result<()> {
-        for path in collect_repositories(root, depth, &self.config.exclude_patterns) {
-            match self.new_repository_from_path(&path) {
+        let paths = self.collect_repositories(root, depth);
+        self.import_paths(paths);
+        Ok(())
+    }
+
+    fn collect_repositories<P: AsRef<Path>>(&self, root: P, depth: Option<usize>) -> Vec<PathBuf> {
+        let trash_dir = self.config.trash_dir();
+        collect_repositories(root, depth, &self.config.exclude_patterns)
+            .into_iter()
+            .filter(|path| !path.starts_with(&trash_dir))
+            .collect()
+    }
+
+    fn import_paths(&mut self, mut paths: Vec<PathBuf>) {
+        // The repositories are inspected in parallel, but added in the order of their paths
+        // so that the result does not depend on the scheduling.
+        paths.sort();
+        let repos = self.pool().map(paths, |path| {
+            let repo = new_repository_from_path(&path).map(|repo| {
+                repo.map(|mut repo| {
+                    repo.load_metadata();
+                    repo
+                })
+            });
+            (path, repo)
+        });
+        for (path, repo) in repos {
+            match repo {
                 Ok(Some(repo)) => self.add_repository(repo),
                 Ok(None) => {
                     self.printer.print(format_args!(
@@ -65,16 +109,39 @@ impl<'ws> Workspace<'ws> {
                     .print(format_args!("{} {}\n", e, path.display())),
             }
         }
+    }
+
+    /// Scan the root directories and `includes` to rebuild the list of repositories.
+    pub fn rebuild_repositories(&mut self) -> Result<()> {
+        let mut roots = self.config.scan_dirs();
+        roots.push(self.config.root_dir.clone());
+        roots.sort();
+        roots.dedup();
+        for root in roots.into_iter().filter(|root| root.is_dir()) {
+            self.import_repositories(root, None)?;
+        }
         Ok(())
     }
 
-    pub fn add_repository(&mut self, repo: Repository) {
+    pub fn add_repository(&mut self, mut repo: Repository) {
+        if let Some(remote) = repo.remote() {
+            for other in self.find_by_remote(remote) {
+                if !other.is_same_local(&repo) {
+                    self.printer.print(format_args!(
+                        "[warn] The same remote is also managed at {}\n",
+                        other.path_string()
+                    ));
+                }
+            }
+        }
+
         let repos = &mut self.cache.get_mut().repositories;
         if let Some(r) = repos.iter_mut().find(|r| r.is_same_local(&repo)) {
             self.printer.print(format_args!(
                 "Overwrite existed entry: {}\n",
                 repo.path_string()
             ));
+            repo.inherit_timestamps(r);
             *r = repo;
             return;
         }
@@ -85,7 +152,7 @@ impl<'ws> Workspace<'ws> {
     }
 
     pub fn add_repository_if_exists(&mut self, path: &Path) -> Result<()> {
-        let repo = match self.new_repository_from_path(path) {
+        let mut repo = match new_repository_from_path(path) {
             Ok(Some(repo)) => repo,
             Ok(None) => {
                 self.printer.print(format_args!(
@@ -100,24 +167,27 @@ impl<'ws> Workspace<'ws> {
                 return Ok(());
             }
         };
+        repo.load_metadata();
         self.add_repository(repo);
         Ok(())
     }
 
     pub fn drop_invalid_repositories(&mut self) {
+        let repos = std::mem::take(&mut self.cache.get_mut().repositories);
         let mut new_repo = Vec::new();
-        for repo in &self.cache.get_mut().repositories {
-            let repo = match repo.clone().refresh() {
-                Some(r) => r,
-                None => continue,
-            };
+        for repo in self
+            .pool()
+            .map(repos, Repository::refresh)
+            .into_iter()
+            .flatten()
+        {
             if self
                 .config
                 .exclude_patterns
                 .iter()
                 .all(|ex| !ex.matches(&repo.path_string()))
             {
-                new_repo.push(repo.clone());
+                new_repo.push(repo);
             } else {
                 self.printer
                     .print(format_args!("Dropped: {}\n", repo.path_string()));
@@ -126,11 +196,185 @@ impl<'ws> Workspace<'ws> {
         self.cache.get_mut().repositories = new_repo;
     }
 
-    pub fn sort_repositories(&mut self) {
-        self.cache
+    /// Returns the managed repositories cloned from `remote`.
+    pub fn find_by_remote<'a, 'r>(
+        &'a self,
+        remote: &'r Remote,
+    ) -> impl Iterator<Item = &'a Repository> + 'r
+    where
+        'a: 'r,
+    {
+        self.repositories()
+            .into_iter()
+            .flatten()
+            .filter(move |repo| repo.has_remote(remote))
+    }
+
+    /// Find a managed repository from a local path, a query or its name.
+    pub fn find_repository(&self, target: &str) -> Result<Option<&Repository>> {
+        if let Some(repo) = self.find_by_location(target).into_iter().next() {
+            return Ok(Some(repo));
+        }
+
+        let mut candidates = self.find_by_name(target).into_iter();
+        match (candidates.next(), candidates.next()) {
+            (Some(repo), None) => Ok(Some(repo)),
+            (Some(first), Some(second)) => {
+                let mut paths = vec![first.path_string(), second.path_string()];
+                paths.extend(candidates.map(Repository::path_string));
+                Err(anyhow!(
+                    "'{}' is ambiguous; candidates are:\n  {}",
+                    target,
+                    paths.join("\n  ")
+                ))
+            }
+            (None, _) => Ok(None),
+        }
+    }
+
+    /// Returns all managed repositories matching a local path, a query or a name.
+    ///
+    /// The repositories matched by their location come first.
+    pub fn find_repositories(&self, target: &str) -> Vec<&Repository> {
+        let mut repos = self.find_by_location(target);
+        for repo in self.find_by_name(target) {
+            if !repos.iter().any(|r| r.is_same_local(repo)) {
+                repos.push(repo);
+            }
+        }
+        repos
+    }
+
+    fn find_by_location(&self, target: &str) -> Vec<&Repository> {
+        let repos = match self.repositories() {
+            Some(repos) => repos,
+            None => return vec![],
+        };
+        let mut found: Vec<&Repository> = Vec::new();
+
+        if let Ok(path) = util::canonicalize_pretty(target) {
+            found.extend(repos.iter().filter(|repo| repo.path() == path));
+        }
+
+        if let Ok(query) = target.parse::<Query>() {
+            if let Ok(path) = self.resolve_query(&query, None) {
+                let path = util::canonicalize_pretty(&path).unwrap_or(path);
+                found.extend(repos.iter().filter(|repo| repo.path() == path));
+            }
+            if let Ok(remote) = self.resolve_remote(&query, None) {
+                found.extend(self.find_by_remote(&remote));
+            }
+        }
+
+        let mut unique: Vec<&Repository> = Vec::with_capacity(found.len());
+        for repo in found {
+            if !unique.iter().any(|r| r.is_same_local(repo)) {
+                unique.push(repo);
+            }
+        }
+        unique
+    }
+
+    /// Returns the repositories whose name is `name`,
+    /// or whose path ends with `name` if it has multiple components (e.g. `owner/repo`).
+    fn find_by_name(&self, name: &str) -> Vec<&Repository> {
+        let is_suffix = name.trim_matches('/').contains('/');
+        self.repositories()
+            .into_iter()
+            .flatten()
+            .filter(|repo| {
+                repo.name() == name || (is_suffix && repo.path().ends_with(name.trim_matches('/')))
+            })
+            .collect()
+    }
+
+    /// Returns the managed repositories whose name or path matches `pattern`.
+    ///
+    /// The path is relative to the root directory which contains the repository, if any.
+    /// The pattern is a glob if it contains any wildcard, and a case-insensitive
+    /// substring otherwise.
+    pub fn find_by_pattern(&self, pattern: &str) -> Vec<&Repository> {
+        let relative_path = |repo: &Repository| {
+            self.config
+                .root_dir_of(repo.path())
+                .and_then(|root| repo.path().strip_prefix(root).ok())
+                .map_or_else(|| repo.path_string(), |path| path.display().to_string())
+        };
+        let repos = self.repositories().into_iter().flatten();
+        if pattern.contains(['*', '?', '[']) {
+            let glob = match Pattern::new(pattern) {
+                Ok(glob) => glob,
+                Err(_) => return vec![],
+            };
+            repos
+                .filter(|repo| glob.matches(repo.name()) || glob.matches(&relative_path(repo)))
+                .collect()
+        } else {
+            let pattern = pattern.to_lowercase();
+            repos
+                .filter(|repo| relative_path(repo).to_lowercase().contains(&pattern))
+                .collect()
+        }
+    }
+
+    /// Drop the repository located at `path` from the managed list.
+    pub fn remove_repository(&mut self, path: &Path) -> Option<Repository> {
+        let repos = &mut self.cache.get_mut().repositories;
+        let index = repos.iter().position(|repo| repo.path() == path)?;
+        let repo = repos.remove(index);
+        self.printer
+            .print(format_args!("Removed entry: {}\n", repo.path_string()));
+        Some(repo)
+    }
+
+    /// Move the working tree of a repository into the trash directory.
+    pub fn trash_repository(&self, repo: &Repository) -> Result<PathBuf> {
+        let trash_dir = self.config.trash_dir();
+        fs::create_dir_all(&trash_dir)?;
+        let dest = trash_dir.join(format!(
+            "{}-{}",
+            repo.name(),
+            Local::now().format("%Y%m%d%H%M%S")
+        ));
+        fs::rename(repo.path(), &dest).with_context(|| {
+            format!(
+                "failed to move {} into {}",
+                repo.path_string(),
+                dest.display()
+            )
+        })?;
+        self.printer.print(format_args!(
+            "Moved {} into {}\n",
+            repo.path_string(),
+            dest.display()
+        ));
+        Ok(dest)
+    }
+
+    /// Delete the working tree of a repository.
+    pub fn purge_repository(&self, repo: &Repository) -> Result<()> {
+        fs::remove_dir_all(repo.path())
+            .with_context(|| format!("failed to delete {}", repo.path_string()))?;
+        self.printer
+            .print(format_args!("Deleted {}\n", repo.path_string()));
+        Ok(())
+    }
+
+    /// Record the access to the repository at `path`, used for sorting by recency.
+    pub fn touch_repository(&mut self, path: &Path) {
+        if let Some(repo) = self
+            .cache
             .get_mut()
             .repositories
-            .sort_by(|a, b| a.name().cmp(b.name()));
+            .iter_mut()
+            .find(|repo| repo.path() == path)
+        {
+            repo.touch();
+        }
+    }
+
+    pub fn sort_repositories(&mut self, key: SortKey, reverse: bool) {
+        key.sort(&mut self.cache.get_mut().repositories, reverse);
     }
 
     /// Save current state of workspace to cache file.
@@ -139,10 +383,21 @@ impl<'ws> Workspace<'ws> {
         Ok(())
     }
 
+    /// Save the cache file unless another process is using it.
+    ///
+    /// Returns whether the cache file was saved.
+    pub fn try_save_cache(&mut self) -> Result<bool> {
+        self.cache.try_dump(&self.config.cache_dir())
+    }
+
     pub fn resolve_query(&self, query: &Query, root_dir: Option<&Path>) -> Result<PathBuf> {
-        let root = root_dir.unwrap_or_else(|| &*self.config.root_dir);
+        let query = &self.expand_alias(query);
         let host = query.host().unwrap_or_else(|| &self.config.host);
-        let path = root.join(host).join(query.path());
+        let root = root_dir.unwrap_or_else(|| self.config.root_dir_for(host));
+        let path = self
+            .config
+            .layout_for(host)
+            .render(root, host, query.path());
         Ok(path)
     }
 
@@ -150,26 +405,47 @@ impl<'ws> Workspace<'ws> {
         &self.config.host
     }
 
-    fn new_repository_from_path(&self, path: &Path) -> Result<Option<Repository>> {
-        let vcs = match vcs::detect_from_path(&path) {
-            Some(vcs) => vcs,
-            None => return Ok(None),
-        };
-        let remote = match vcs.get_remote_url(&path)? {
-            Some(remote) => remote,
-            None => return Ok(None),
-        };
-        Repository::new(path, vcs, Remote::new(remote)).map(Some)
+    /// Expand the host alias in the query, if any.
+    pub fn expand_alias<'q>(&self, query: &'q Query) -> Cow<'q, Query> {
+        match self.config.expand_alias(query) {
+            Some(query) => Cow::Owned(query),
+            None => Cow::Borrowed(query),
+        }
+    }
+
+    /// Determine the URL of remote repository from a query.
+    ///
+    /// If `protocol` is omitted, the one configured for the host is used.
+    pub fn resolve_remote(&self, query: &Query, protocol: Option<Protocol>) -> Result<Remote> {
+        let query = &self.expand_alias(query);
+        let host = query.host().unwrap_or_else(|| self.default_host());
+        let host_config = self.config.host_config(host);
+        let protocol = protocol
+            .or_else(|| host_config.and_then(|config| config.protocol))
+            .unwrap_or(Protocol::Https);
+        let username = host_config.and_then(|config| config.username.as_deref());
+        Remote::from_query(query, protocol == Protocol::Ssh, host, username)
+    }
+
+    /// Returns the VCS used for repositories on `host` unless specified explicitly.
+    pub fn default_vcs(&self, query: &Query) -> Vcs {
+        let query = &self.expand_alias(query);
+        let host = query.host().unwrap_or_else(|| self.default_host());
+        self.config
+            .host_config(host)
+            .and_then(|config| config.vcs)
+            .unwrap_or(Vcs::GIT)
     }
 
     pub fn create_repository(
         &mut self,
         query: &Query,
-        vcs: Vcs,
-        is_ssh: bool,
+        vcs: Option<Vcs>,
+        protocol: Option<Protocol>,
         root: Option<&Path>,
     ) -> Result<()> {
         let path = self.resolve_query(query, root)?;
+        let vcs = vcs.unwrap_or_else(|| self.default_vcs(query));
 
         self.printer.print(format_args!(
             "Creating an empty repository at \"{}\" (VCS: {:?})\n",
@@ -184,7 +460,7 @@ impl<'ws> Workspace<'ws> {
             return Ok(());
         }
         vcs.do_init(&path)?;
-        let remote = Remote::from_query(&query, is_ssh, self.default_host()).ok();
+        let remote = self.resolve_remote(query, protocol).ok();
         if let Some(ref remote) = remote {
             vcs.set_remote_url(&path, remote.url())?;
         }
@@ -194,6 +470,9 @@ impl<'ws> Workspace<'ws> {
         Ok(())
     }
 
+    /// Clone the remote repository into `dest`.
+    ///
+    /// The remote is cloned even if it is already managed at another location (see `find_by_remote`).
     pub fn clone_repository(&mut self, remote: Remote, dest: &Path, vcs: Vcs) -> Result<()> {
         self.printer.print(format_args!(
             "[info] Clone from {} into {} by using {:?}\n",
@@ -201,20 +480,32 @@ impl<'ws> Workspace<'ws> {
             dest.display(),
             vcs,
         ));
-        if vcs::detect_from_path(&dest).is_some() {
+        if vcs::detect_from_path(dest).is_some() {
             self.printer.print(format_args!(
                 "The repository {} has already existed.\n",
                 dest.display()
             ));
             return Ok(());
         }
-        vcs.do_clone(&dest, &remote.url(), &[] as &[String])?;
+        vcs.do_clone(dest, remote.url(), &[] as &[String])?;
         let repo = Repository::new(dest, vcs, remote)?;
         self.add_repository(repo);
         Ok(())
     }
 }
 
+fn new_repository_from_path(path: &Path) -> Result<Option<Repository>> {
+    let vcs = match vcs::detect_from_path(path) {
+        Some(vcs) => vcs,
+        None => return Ok(None),
+    };
+    let remote = match vcs.get_remote_url(path)? {
+        Some(remote) => remote,
+        None => return Ok(None),
+    };
+    Repository::new(path, vcs, Remote::new(remote)?).map(Some)
+}
+
 fn collect_repositories<P>(root: P, depth: Option<usize>, excludes: &[Pattern]) -> Vec<PathBuf>
 where
     P: AsRef<Path>,
@@ -228,7 +519,7 @@ where
             !entry
                 .path()
                 .parent()
-                .map(|path| vcs::detect_from_path(&path).is_some())
+                .map(|path| vcs::detect_from_path(path).is_some())
                 .unwrap_or(false)
                 && entry
                     .path()